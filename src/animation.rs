@@ -1,28 +1,119 @@
+use std::collections::HashMap;
+
+use crate::assets::{Assets, Defs};
 use crate::vecs::Vec2;
 use crate::Camera;
-use macroquad::prelude::{
-    draw_texture_ex, get_frame_time, DrawTextureParams, Rect, Texture2D, WHITE,
-};
+use macroquad::prelude::{draw_texture_ex, DrawTextureParams, Rect, WHITE};
+use serde::Deserialize;
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Mode {
+    #[default]
+    Loop,
+    Once,
+    PingPong,
+}
+
+/// One clip of an animation in the manifest. Frame size and count default to
+/// the ones declared for its texture.
+#[derive(Deserialize, Clone)]
+pub struct ClipDef {
+    pub name: String,
+    pub texture: String,
+    pub frame_width: Option<u32>,
+    pub frame_height: Option<u32>,
+    pub frames: Option<usize>,
+    /// Seconds per frame: one value for every frame, or one per frame.
+    pub durations: Vec<f32>,
+    #[serde(default)]
+    pub mode: Mode,
+    /// Named frames that fire an event when the clip reaches them.
+    #[serde(default)]
+    pub markers: HashMap<String, usize>,
+}
+
+/// What happened during the last `AnimatedSprite::update`.
+#[derive(Clone, PartialEq)]
+pub enum Event {
+    /// The named clip played its last frame. Looping clips fire this once
+    /// per cycle.
+    Finished(String),
+    Marker(String),
+}
 
+#[derive(Clone)]
 pub struct AnimatedSprite {
     pub animations: Vec<Animation>,
     pub cur_animation: usize,
-    pub dur: f32,
     pub time: f32,
     pub playing: bool,
+    pub events: Vec<Event>,
 }
 
 impl AnimatedSprite {
-    pub fn update(&mut self) {
-        self.time += get_frame_time();
-        self.animations[self.cur_animation].update(&mut self.time, &self.dur, &self.playing);
+    pub fn from_def(clips: &[ClipDef], defs: &Defs) -> Self {
+        AnimatedSprite {
+            animations: clips
+                .iter()
+                .map(|clip| Animation::from_def(clip, defs))
+                .collect(),
+            cur_animation: 0,
+            time: 0.,
+            playing: true,
+            events: Vec::new(),
+        }
+    }
+
+    /// Switches to the clip called `name`, keeping its current frame.
+    pub fn play(&mut self, name: &str) {
+        if let Some(i) = self.animations.iter().position(|a| a.name == name) {
+            self.cur_animation = i;
+        }
     }
 
-    pub fn draw(&self, pos: &Vec2, size: &f32, flipped: &bool, camera: &Camera) {
+    /// Rewinds the current clip to its first frame and plays it.
+    pub fn restart(&mut self) {
+        let animation = &mut self.animations[self.cur_animation];
+        animation.cur_frame = 0;
+        animation.reverse = false;
+        self.time = 0.;
+        self.playing = true;
+    }
+
+    /// Whether the last update finished the clip called `clip`.
+    pub fn finished(&self, clip: &str) -> bool {
+        self.events
+            .iter()
+            .any(|event| matches!(event, Event::Finished(name) if name == clip))
+    }
+
+    /// Whether the last update reached the frame marked `marker`.
+    pub fn fired(&self, marker: &str) -> bool {
+        self.events
+            .iter()
+            .any(|event| matches!(event, Event::Marker(name) if name == marker))
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.events.clear();
+        if self.playing {
+            self.time += dt;
+            let animation = &mut self.animations[self.cur_animation];
+            animation.update(&mut self.time, &mut self.events);
+            //one-shot clips hold their last frame until restarted
+            let once = animation.mode == Mode::Once;
+            if once && self.events.iter().any(|e| matches!(e, Event::Finished(_))) {
+                self.playing = false;
+            }
+        }
+    }
+
+    pub fn draw(&self, pos: &Vec2, size: &f32, flipped: &bool, assets: &Assets, camera: &Camera) {
         let animation = &self.animations[self.cur_animation];
 
         let params = DrawTextureParams {
-            source: Some(animation.rect),
+            source: Some(animation.rect()),
             dest_size: Some(macroquad::prelude::Vec2::new(
                 animation.width as f32 * size * camera.zoom,
                 animation.height as f32 * size * camera.zoom,
@@ -32,7 +123,7 @@ impl AnimatedSprite {
         };
 
         draw_texture_ex(
-            animation.texture,
+            assets.texture(&animation.texture),
             pos.x * camera.zoom + camera.pos.x,
             pos.y * camera.zoom + camera.pos.y,
             WHITE,
@@ -43,30 +134,149 @@ impl AnimatedSprite {
 
 #[derive(Clone)]
 pub struct Animation {
-    pub texture: Texture2D,
+    pub name: String,
+    /// The id of the texture it is drawn from.
+    pub texture: String,
     pub width: usize,
     pub height: usize,
     pub frames: usize,
     pub cur_frame: usize,
-    pub rect: Rect,
+    pub durations: Vec<f32>,
+    pub mode: Mode,
+    pub markers: HashMap<String, usize>,
+    pub reverse: bool,
 }
 
 impl Animation {
-    pub fn update(&mut self, time: &mut f32, dur: &f32, playing: &bool) {
-        if *time + get_frame_time() > *dur {
-            *time += get_frame_time() - dur;
-            if *playing {
-                self.cur_frame += 1;
-                if self.cur_frame == self.frames {
-                    self.cur_frame = 0;
+    pub fn from_def(clip: &ClipDef, defs: &Defs) -> Self {
+        let sheet = defs.sheet(&clip.texture);
+        let frames = clip.frames.unwrap_or(sheet.frames);
+        //one frame has nowhere to bounce to, so it would never finish
+        let mode = match clip.mode {
+            Mode::PingPong if frames == 1 => Mode::Once,
+            mode => mode,
+        };
+        Animation {
+            name: clip.name.clone(),
+            texture: clip.texture.clone(),
+            width: clip.frame_width.map_or(sheet.frame_width, |w| w as f32) as usize,
+            height: clip.frame_height.map_or(sheet.frame_height, |h| h as f32) as usize,
+            frames,
+            cur_frame: 0,
+            durations: clip.durations.clone(),
+            mode,
+            markers: clip.markers.clone(),
+            reverse: false,
+        }
+    }
+
+    pub fn rect(&self) -> Rect {
+        Rect::new(
+            (self.cur_frame * self.width) as f32,
+            0.,
+            self.width as f32,
+            self.height as f32,
+        )
+    }
+
+    fn duration(&self) -> f32 {
+        if self.durations.len() == self.frames {
+            self.durations[self.cur_frame]
+        } else {
+            self.durations[0]
+        }
+    }
+
+    pub fn update(&mut self, time: &mut f32, events: &mut Vec<Event>) {
+        while *time >= self.duration() {
+            *time -= self.duration();
+            let last = self.cur_frame + 1 == self.frames;
+            match self.mode {
+                Mode::Loop => {
+                    if last {
+                        events.push(Event::Finished(self.name.clone()));
+                    }
+                    self.cur_frame = (self.cur_frame + 1) % self.frames;
+                }
+                Mode::Once => {
+                    if last {
+                        *time = 0.;
+                        events.push(Event::Finished(self.name.clone()));
+                        return;
+                    }
+                    self.cur_frame += 1;
+                }
+                Mode::PingPong => {
+                    if self.frames > 1 {
+                        if self.cur_frame == 0 {
+                            self.reverse = false;
+                        } else if last {
+                            self.reverse = true;
+                        }
+                    }
+                    if self.reverse {
+                        self.cur_frame -= 1;
+                        if self.cur_frame == 0 {
+                            events.push(Event::Finished(self.name.clone()));
+                        }
+                    } else {
+                        self.cur_frame = (self.cur_frame + 1) % self.frames;
+                    }
+                }
+            }
+
+            for (marker, frame) in &self.markers {
+                if *frame == self.cur_frame {
+                    events.push(Event::Marker(marker.clone()));
                 }
-                self.rect = Rect::new(
-                    (self.cur_frame * self.width) as f32,
-                    0.,
-                    self.width as f32,
-                    self.height as f32,
-                );
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assets::Sheet;
+
+    fn sprite(frames: usize, mode: Mode) -> AnimatedSprite {
+        let mut defs = Defs::default();
+        let sheet = Sheet {
+            path: String::new(),
+            frame_width: 8.,
+            frame_height: 8.,
+            frames,
+        };
+        defs.sheets.insert("sheet".to_string(), sheet);
+        let clip = ClipDef {
+            name: "clip".to_string(),
+            texture: "sheet".to_string(),
+            frame_width: None,
+            frame_height: None,
+            frames: None,
+            durations: vec![0.1],
+            mode,
+            markers: HashMap::new(),
+        };
+        AnimatedSprite::from_def(&[clip], &defs)
+    }
+
+    #[test]
+    fn ping_pong_finishes_back_on_its_first_frame() {
+        let mut sprite = sprite(3, Mode::PingPong);
+        let mut frames = Vec::new();
+        while !sprite.finished("clip") {
+            sprite.update(0.1);
+            frames.push(sprite.animations[0].cur_frame);
+        }
+        assert_eq!(frames, [1, 2, 1, 0]);
+    }
+
+    #[test]
+    fn one_frame_ping_pong_finishes() {
+        let mut sprite = sprite(1, Mode::PingPong);
+        sprite.update(0.1);
+        assert!(sprite.finished("clip"));
+        assert!(!sprite.playing);
+    }
+}
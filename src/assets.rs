@@ -0,0 +1,494 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use macroquad::audio::{load_sound_from_bytes, Sound};
+use macroquad::prelude::{load_file, load_string, Color, FilterMode, Image, Texture2D, BLACK};
+use serde::Deserialize;
+
+use crate::animation::ClipDef;
+use crate::map::TileDef;
+
+pub const MANIFEST_PATH: &str = "assets/manifest.json";
+
+/// Textures the code asks for by id, checked when the manifest loads so a
+/// missing one stops the game there instead of crashing it later.
+const REQUIRED_TEXTURES: [&str; 15] = [
+    "bad_ending",
+    "exit",
+    "flecha",
+    "good_ending",
+    "got_shield",
+    "got_skill",
+    "got_sword",
+    "heart",
+    "menu_art",
+    "new_game",
+    "player_skill",
+    "quadrinho_1",
+    "quadrinho_2",
+    "quadrinho_3",
+    "shield",
+];
+/// Animations the code asks for by id, like `REQUIRED_TEXTURES`.
+const REQUIRED_ANIMATIONS: [&str; 10] = [
+    "boar",
+    "boar_dust",
+    "buffalo",
+    "coruja",
+    "coruja_ball",
+    "crow",
+    "crystal",
+    "player",
+    "skill",
+    "sword",
+];
+const REQUIRED_LAYERS: [&str; 2] = ["floors", "walls"];
+
+/// Every asset that failed to load, so they can all be fixed in one go.
+#[derive(Debug, Default)]
+pub struct AssetError {
+    pub missing: Vec<String>,
+    pub corrupt: Vec<(String, String)>,
+    /// Failures nothing can stand in for, like an unreadable manifest or
+    /// clips and tiles that make no sense. These stop the game even in
+    /// fallback mode.
+    pub fatal: Vec<(String, String)>,
+}
+
+impl AssetError {
+    pub fn is_empty(&self) -> bool {
+        self.missing.is_empty() && self.corrupt.is_empty() && self.fatal.is_empty()
+    }
+}
+
+impl fmt::Display for AssetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if !self.missing.is_empty() {
+            writeln!(f, "missing assets:")?;
+            for path in &self.missing {
+                writeln!(f, "  {}", path)?;
+            }
+        }
+        if !self.corrupt.is_empty() {
+            writeln!(f, "corrupt assets:")?;
+            for (path, reason) in &self.corrupt {
+                writeln!(f, "  {}: {}", path, reason)?;
+            }
+        }
+        if !self.fatal.is_empty() {
+            writeln!(f, "broken assets:")?;
+            for (path, reason) in &self.fatal {
+                writeln!(f, "  {}: {}", path, reason)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Loads textures and sounds while collecting failures instead of panicking on the
+/// first one. With `fallback` set, failed textures are replaced by a magenta
+/// checkerboard so the game still boots.
+pub struct Loader {
+    pub fallback: bool,
+    pub error: AssetError,
+}
+
+impl Loader {
+    pub fn new(fallback: bool) -> Self {
+        Self {
+            fallback,
+            error: AssetError::default(),
+        }
+    }
+
+    pub async fn texture(&mut self, path: &str) -> Texture2D {
+        self.image(path)
+            .await
+            .map(|image| Texture2D::from_rgba8(image.width() as u16, image.height() as u16, &image))
+            .unwrap_or_else(|| self.placeholder())
+    }
+
+    /// Decodes an mp3 or m4a file, cut to the `start`..`end` seconds, into a
+    /// playable sound. Failed sounds are reported like textures but have no
+    /// placeholder; the game plays silence instead.
+    pub async fn sound(&mut self, path: &str, start: f32, end: Option<f32>) -> Option<Sound> {
+        let bytes = match load_file(path).await {
+            Ok(bytes) => bytes,
+            Err(_) => {
+                self.error.missing.push(path.to_string());
+                return None;
+            }
+        };
+
+        match crate::audio::decode(bytes) {
+            Ok(mut pcm) => {
+                pcm.slice(start, end);
+                load_sound_from_bytes(&pcm.to_wav()).await.ok()
+            }
+            Err(err) => {
+                self.error.corrupt.push((path.to_string(), err));
+                None
+            }
+        }
+    }
+
+    async fn image(&mut self, path: &str) -> Option<image::RgbaImage> {
+        let bytes = match load_file(path).await {
+            Ok(bytes) => bytes,
+            Err(_) => {
+                self.error.missing.push(path.to_string());
+                return None;
+            }
+        };
+
+        match image::load_from_memory(&bytes) {
+            Ok(image) => Some(image.to_rgba8()),
+            Err(err) => {
+                self.error.corrupt.push((path.to_string(), err.to_string()));
+                None
+            }
+        }
+    }
+
+    fn placeholder(&self) -> Texture2D {
+        if !self.fallback {
+            return Texture2D::empty();
+        }
+
+        let magenta = Color::new(1., 0., 1., 1.);
+        let mut image = Image::gen_image_color(64, 64, BLACK);
+        for y in 0..64 {
+            for x in 0..64 {
+                if (x / 8 + y / 8) % 2 == 0 {
+                    image.set_pixel(x, y, magenta);
+                }
+            }
+        }
+        let texture = Texture2D::from_image(&image);
+        texture.set_filter(FilterMode::Nearest);
+        texture
+    }
+
+    /// Hands back `value` unless something failed to load. In fallback mode
+    /// failures that have a placeholder are only reported.
+    pub fn finish<T>(self, value: T) -> Result<T, AssetError> {
+        if self.error.is_empty() {
+            Ok(value)
+        } else if self.fallback && self.error.fatal.is_empty() {
+            println!("{}", self.error);
+            Ok(value)
+        } else {
+            Err(self.error)
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Filter {
+    Linear,
+    Nearest,
+}
+
+/// One line of the manifest: where a texture lives and, for sprite sheets,
+/// the size and number of its frames.
+#[derive(Deserialize, Clone)]
+pub struct TextureDef {
+    pub id: String,
+    pub path: String,
+    #[serde(default = "linear")]
+    pub filter: Filter,
+    pub frame_width: Option<u32>,
+    pub frame_height: Option<u32>,
+    pub frames: Option<u32>,
+}
+
+fn linear() -> Filter {
+    Filter::Linear
+}
+
+#[derive(Deserialize)]
+pub struct Manifest {
+    pub textures: Vec<TextureDef>,
+    #[serde(default)]
+    pub animations: HashMap<String, Vec<ClipDef>>,
+    #[serde(default)]
+    pub tiles: HashMap<String, Vec<TileDef>>,
+}
+
+/// How a texture splits into frames. Textures that aren't sheets are one
+/// frame the size of the whole image.
+#[derive(Clone)]
+pub struct Sheet {
+    pub path: String,
+    pub frame_width: f32,
+    pub frame_height: f32,
+    pub frames: usize,
+}
+
+/// What the game logic needs from the manifest: frame sizes, animation
+/// clips and tile kinds, without the textures themselves, so a `World` can
+/// be built and run without a window.
+#[derive(Default)]
+pub struct Defs {
+    pub sheets: HashMap<String, Sheet>,
+    pub animations: HashMap<String, Vec<ClipDef>>,
+    pub tiles: HashMap<String, Vec<TileDef>>,
+}
+
+impl Defs {
+    /// Takes the manifest's clips and tiles, with `size` giving the width
+    /// and height in pixels of each texture. Also returns every clip and
+    /// tile that doesn't make sense, and every required id that is missing.
+    pub fn new(
+        manifest: Manifest,
+        size: impl Fn(&TextureDef) -> (u32, u32),
+    ) -> (Self, Vec<String>) {
+        let mut defs = Defs::default();
+        for def in &manifest.textures {
+            let (width, height) = size(def);
+            defs.sheets.insert(
+                def.id.clone(),
+                Sheet {
+                    path: def.path.clone(),
+                    frame_width: def.frame_width.unwrap_or(width) as f32,
+                    frame_height: def.frame_height.unwrap_or(height) as f32,
+                    frames: def.frames.unwrap_or(1) as usize,
+                },
+            );
+        }
+
+        let mut errors = Vec::new();
+        for (id, clips) in &manifest.animations {
+            if clips.is_empty() {
+                errors.push(format!("animation `{}` has no clips", id));
+            }
+            for clip in clips {
+                if let Err(reason) = defs.validate_clip(clip) {
+                    errors.push(format!(
+                        "animation `{}` clip `{}`: {}",
+                        id, clip.name, reason
+                    ));
+                }
+            }
+        }
+        defs.animations = manifest.animations;
+
+        for (layer, tiles) in &manifest.tiles {
+            for (i, def) in tiles.iter().enumerate() {
+                if let Err(reason) = defs.validate_tile(i, def, tiles.len()) {
+                    errors.push(format!("tile {} of layer `{}`: {}", i, layer, reason));
+                }
+            }
+        }
+        defs.tiles = manifest.tiles;
+
+        errors.extend(defs.missing());
+        (defs, errors)
+    }
+
+    /// Every id the code asks for that the manifest doesn't have.
+    fn missing(&self) -> Vec<String> {
+        let textures = REQUIRED_TEXTURES
+            .iter()
+            .filter(|id| !self.sheets.contains_key(**id))
+            .map(|id| format!("texture `{}` is missing", id));
+        let animations = REQUIRED_ANIMATIONS
+            .iter()
+            .filter(|id| !self.animations.contains_key(**id))
+            .map(|id| format!("animation `{}` is missing", id));
+        let layers = REQUIRED_LAYERS
+            .iter()
+            .filter(|layer| !self.tiles.contains_key(**layer))
+            .map(|layer| format!("tile layer `{}` is missing", layer));
+        textures.chain(animations).chain(layers).collect()
+    }
+
+    fn validate_clip(&self, clip: &ClipDef) -> Result<(), String> {
+        let asset = self
+            .sheets
+            .get(&clip.texture)
+            .ok_or(format!("unknown texture `{}`", clip.texture))?;
+        let width = clip.frame_width.map_or(asset.frame_width, |w| w as f32);
+        let frames = clip.frames.unwrap_or(asset.frames);
+        if frames == 0 || width * frames as f32 > asset.frame_width * asset.frames as f32 {
+            return Err(format!(
+                "`{}` has no room for {} frames",
+                clip.texture, frames
+            ));
+        }
+        if clip.durations.is_empty() || clip.durations.iter().any(|d| *d <= 0.) {
+            return Err("durations must be positive and not empty".to_string());
+        }
+        if let Some((marker, frame)) = clip.markers.iter().find(|(_, frame)| **frame >= frames) {
+            return Err(format!(
+                "marker `{}` is on frame {} of {}",
+                marker, frame, frames
+            ));
+        }
+        if clip.durations.len() != 1 && clip.durations.len() != frames {
+            return Err(format!(
+                "{} durations given for {} frames",
+                clip.durations.len(),
+                frames
+            ));
+        }
+        Ok(())
+    }
+
+    fn validate_tile(&self, i: usize, def: &TileDef, kinds: usize) -> Result<(), String> {
+        if def.kind as usize != i {
+            return Err(format!(
+                "kind {} is out of order, tiles are listed by kind from 0",
+                def.kind
+            ));
+        }
+        let known = if def.animated {
+            self.animations.contains_key(&def.sprite)
+        } else {
+            self.sheets.contains_key(&def.sprite)
+        };
+        if !known {
+            return Err(format!("unknown sprite `{}`", def.sprite));
+        }
+        if let Some(next) = def.breaks_into {
+            if next as usize >= kinds || next == def.kind {
+                return Err(format!("can't break into kind {}", next));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn animation(&self, id: &str) -> &[ClipDef] {
+        self.animations
+            .get(id)
+            .unwrap_or_else(|| panic!("animation `{}` is not in the manifest", id))
+    }
+
+    pub fn tiles(&self, layer: &str) -> &[TileDef] {
+        self.tiles
+            .get(layer)
+            .unwrap_or_else(|| panic!("layer `{}` has no tiles in the manifest", layer))
+    }
+
+    pub fn sheet(&self, id: &str) -> &Sheet {
+        self.sheets
+            .get(id)
+            .unwrap_or_else(|| panic!("texture `{}` is not in the manifest", id))
+    }
+}
+
+/// Every texture listed in the manifest, by id, along with the defs the
+/// game logic runs on.
+pub struct Assets {
+    pub textures: HashMap<String, Texture2D>,
+    pub defs: Defs,
+}
+
+impl Assets {
+    pub async fn load(loader: &mut Loader, path: &str) -> Self {
+        let mut assets = Assets {
+            textures: HashMap::new(),
+            defs: Defs::default(),
+        };
+
+        let manifest = match load_string(path).await {
+            Ok(string) => string,
+            Err(_) => {
+                let reason = "could not read file".to_string();
+                loader.error.fatal.push((path.to_string(), reason));
+                return assets;
+            }
+        };
+        let manifest: Manifest = match serde_json::from_str(&manifest) {
+            Ok(manifest) => manifest,
+            Err(err) => {
+                loader.error.fatal.push((path.to_string(), err.to_string()));
+                return assets;
+            }
+        };
+
+        for def in &manifest.textures {
+            let texture = match loader.image(&def.path).await {
+                Some(image) => {
+                    if let Err(reason) = def.validate(image.width(), image.height()) {
+                        loader.error.corrupt.push((def.path.clone(), reason));
+                    }
+                    Texture2D::from_rgba8(image.width() as u16, image.height() as u16, &image)
+                }
+                None => loader.placeholder(),
+            };
+            if def.filter == Filter::Nearest {
+                texture.set_filter(FilterMode::Nearest);
+            }
+            assets.textures.insert(def.id.clone(), texture);
+        }
+
+        let size = |def: &TextureDef| {
+            let texture = assets.textures[&def.id];
+            (texture.width() as u32, texture.height() as u32)
+        };
+        let (defs, errors) = Defs::new(manifest, size);
+        //a bad clip or tile would panic or hang once the game runs it
+        for reason in errors {
+            loader.error.fatal.push((path.to_string(), reason));
+        }
+        assets.defs = defs;
+
+        assets
+    }
+
+    pub fn texture(&self, id: &str) -> Texture2D {
+        *self
+            .textures
+            .get(id)
+            .unwrap_or_else(|| panic!("texture `{}` is not in the manifest", id))
+    }
+}
+
+impl TextureDef {
+    fn validate(&self, width: u32, height: u32) -> Result<(), String> {
+        if let Some(frame_width) = self.frame_width {
+            if frame_width == 0 || !width.is_multiple_of(frame_width) {
+                return Err(format!(
+                    "sheet is {} px wide, not a multiple of the {} px frame width",
+                    width, frame_width
+                ));
+            }
+            let frames = self.frames.unwrap_or(1);
+            if frames * frame_width > width {
+                return Err(format!(
+                    "sheet has room for {} frames but {} are declared",
+                    width / frame_width,
+                    frames
+                ));
+            }
+        }
+        if let Some(frame_height) = self.frame_height {
+            if frame_height > height {
+                return Err(format!(
+                    "frame height {} is taller than the {} px sheet",
+                    frame_height, height
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    /// The real manifest, sized from the image headers, so nothing needs a
+    /// window.
+    pub fn defs() -> Defs {
+        let manifest = std::fs::read_to_string(MANIFEST_PATH).unwrap();
+        let manifest: Manifest = serde_json::from_str(&manifest).unwrap();
+        let (defs, errors) = Defs::new(manifest, |def| {
+            image::image_dimensions(&def.path).unwrap_or((0, 0))
+        });
+        assert!(errors.is_empty(), "{:?}", errors);
+        defs
+    }
+}
@@ -0,0 +1,329 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Cursor, ErrorKind, Write};
+
+use macroquad::audio::{play_sound, set_sound_volume, stop_sound, PlaySoundParams, Sound};
+use macroquad::prelude::{load_string, rand};
+use serde::Deserialize;
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::DecoderOptions;
+use symphonia::core::errors::Error;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+use crate::assets::Loader;
+
+pub const VOLUME_PATH: &str = "assets/config/volume.txt";
+/// Seconds it takes one music track to fade into the next.
+const CROSSFADE: f32 = 1.5;
+
+/// A sound effect; one of its variants is picked at random on every play.
+#[derive(Deserialize)]
+pub struct SoundDef {
+    pub id: String,
+    pub variants: Vec<VariantDef>,
+}
+
+/// A file, or the part of it between `start` and `end` seconds.
+#[derive(Deserialize)]
+pub struct VariantDef {
+    pub path: String,
+    #[serde(default)]
+    pub start: f32,
+    pub end: Option<f32>,
+}
+
+#[derive(Deserialize)]
+pub struct MusicDef {
+    pub id: String,
+    pub path: String,
+}
+
+#[derive(Deserialize)]
+struct AudioManifest {
+    #[serde(default)]
+    sounds: Vec<SoundDef>,
+    #[serde(default)]
+    music: Vec<MusicDef>,
+}
+
+struct Track {
+    id: String,
+    sound: Sound,
+    //0 is silent, 1 is full music volume
+    fade: f32,
+}
+
+/// Plays sound effects and keeps one music track going, crossfading when the
+/// track changes.
+pub struct Audio {
+    pub sounds: HashMap<String, Vec<Sound>>,
+    pub music: HashMap<String, Sound>,
+    pub music_volume: f32,
+    pub sfx_volume: f32,
+    current: Option<Track>,
+    fading: Vec<Track>,
+}
+
+impl Audio {
+    pub async fn load(loader: &mut Loader, path: &str) -> Self {
+        let mut audio = Audio {
+            sounds: HashMap::new(),
+            music: HashMap::new(),
+            music_volume: 1.,
+            sfx_volume: 1.,
+            current: None,
+            fading: Vec::new(),
+        };
+
+        //the textures were already read from this manifest, so it only
+        //fails here if it failed there too
+        let manifest = match load_string(path).await {
+            Ok(string) => string,
+            Err(_) => return audio,
+        };
+        let manifest: AudioManifest = match serde_json::from_str(&manifest) {
+            Ok(manifest) => manifest,
+            Err(_) => return audio,
+        };
+
+        for def in manifest.sounds {
+            let mut variants = Vec::new();
+            for variant in &def.variants {
+                let sound = loader.sound(&variant.path, variant.start, variant.end);
+                if let Some(sound) = sound.await {
+                    variants.push(sound);
+                }
+            }
+            audio.sounds.insert(def.id, variants);
+        }
+        for def in manifest.music {
+            if let Some(sound) = loader.sound(&def.path, 0., None).await {
+                audio.music.insert(def.id, sound);
+            }
+        }
+
+        audio
+    }
+
+    /// Reads `Music = 0.8` and `Sfx = 1.0` lines; anything missing stays at
+    /// full volume.
+    pub async fn load_volume(&mut self, path: &str) {
+        let string = match load_string(path).await {
+            Ok(string) => string,
+            Err(_) => return,
+        };
+        for line in string.lines() {
+            let (name, value) = match line.split_once('=') {
+                Some(pair) => pair,
+                None => continue,
+            };
+            let value = match value.trim().parse::<f32>() {
+                Ok(value) => value.clamp(0., 1.),
+                Err(_) => continue,
+            };
+            match name.trim() {
+                "Music" => self.music_volume = value,
+                "Sfx" => self.sfx_volume = value,
+                _ => (),
+            }
+        }
+    }
+
+    pub fn save_volume(&self, path: &str) -> io::Result<()> {
+        let mut output = fs::File::create(path)?;
+        writeln!(output, "Music = {}", self.music_volume)?;
+        writeln!(output, "Sfx = {}", self.sfx_volume)
+    }
+
+    /// Plays a random variant of the sound effect `id`. Unknown ids are
+    /// ignored so a missing file never stops the game.
+    pub fn play(&self, id: &str) {
+        if let Some(variants) = self.sounds.get(id) {
+            if !variants.is_empty() {
+                let i = rand::gen_range(0, variants.len());
+                play_sound(
+                    variants[i],
+                    PlaySoundParams {
+                        looped: false,
+                        volume: self.sfx_volume,
+                    },
+                );
+            }
+        }
+    }
+
+    /// Switches the music to `id`, fading out whatever was playing.
+    pub fn play_music(&mut self, id: &str) {
+        if self.current.as_ref().map(|track| track.id.as_str()) == Some(id) {
+            return;
+        }
+        if let Some(track) = self.current.take() {
+            self.fading.push(track);
+        }
+
+        //a track that is still fading out fades back in from where it was
+        let track = match self.fading.iter().position(|track| track.id == id) {
+            Some(i) => Some(self.fading.remove(i)),
+            None => self.music.get(id).map(|sound| {
+                play_sound(
+                    *sound,
+                    PlaySoundParams {
+                        looped: true,
+                        volume: 0.,
+                    },
+                );
+                Track {
+                    id: id.to_string(),
+                    sound: *sound,
+                    fade: 0.,
+                }
+            }),
+        };
+        self.current = track;
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        let step = dt / CROSSFADE;
+        if let Some(track) = &mut self.current {
+            track.fade = (track.fade + step).min(1.);
+            set_sound_volume(track.sound, track.fade * self.music_volume);
+        }
+        for track in &mut self.fading {
+            track.fade = (track.fade - step).max(0.);
+            set_sound_volume(track.sound, track.fade * self.music_volume);
+            if track.fade == 0. {
+                stop_sound(track.sound);
+            }
+        }
+        self.fading.retain(|track| track.fade > 0.);
+    }
+}
+
+/// Decoded 16-bit samples, interleaved when stereo.
+pub struct Pcm {
+    pub samples: Vec<i16>,
+    pub channels: u16,
+    pub rate: u32,
+}
+
+/// Decodes an mp3 or m4a file. The mixer only takes mono or stereo, so any
+/// extra channels are dropped.
+pub fn decode(mut bytes: Vec<u8>) -> Result<Pcm, String> {
+    trim_mp4(&mut bytes)?;
+    let stream = MediaSourceStream::new(Box::new(Cursor::new(bytes)), Default::default());
+    let probed = symphonia::default::get_probe()
+        .format(
+            &Hint::new(),
+            stream,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .map_err(|err| err.to_string())?;
+    let mut format = probed.format;
+    let track = format.default_track().ok_or("no audio track")?;
+    let track_id = track.id;
+    let mut decoder = symphonia::default::get_codecs()
+        .make(&track.codec_params, &DecoderOptions::default())
+        .map_err(|err| err.to_string())?;
+
+    let mut pcm = Pcm {
+        samples: Vec::new(),
+        channels: 0,
+        rate: 0,
+    };
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(Error::IoError(err)) if err.kind() == ErrorKind::UnexpectedEof => break,
+            Err(err) => return Err(err.to_string()),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            //a damaged frame is skipped, like every player does
+            Err(Error::DecodeError(_)) => continue,
+            Err(err) => return Err(err.to_string()),
+        };
+        let spec = *decoded.spec();
+        let channels = spec.channels.count();
+        pcm.channels = channels.min(2) as u16;
+        pcm.rate = spec.rate;
+
+        let mut buffer = SampleBuffer::<i16>::new(decoded.capacity() as u64, spec);
+        buffer.copy_interleaved_ref(decoded);
+        for frame in buffer.samples().chunks(channels) {
+            pcm.samples.extend(frame.iter().take(2));
+        }
+    }
+    if pcm.samples.is_empty() {
+        return Err("no audio samples".to_string());
+    }
+
+    Ok(pcm)
+}
+
+/// Some of the recorded m4a files carry junk after their last atom, which
+/// the demuxer chokes on. Cuts the file after the last well formed atom.
+fn trim_mp4(bytes: &mut Vec<u8>) -> Result<(), String> {
+    if bytes.get(4..8) != Some(b"ftyp") {
+        return Ok(());
+    }
+    let mut end: usize = 0;
+    while end + 8 <= bytes.len() {
+        let mut size = u32::from_be_bytes(bytes[end..end + 4].try_into().unwrap()) as u64;
+        if size == 1 && end + 16 <= bytes.len() {
+            size = u64::from_be_bytes(bytes[end + 8..end + 16].try_into().unwrap());
+        }
+        let next = usize::try_from(size)
+            .ok()
+            .and_then(|size| end.checked_add(size))
+            .ok_or(format!("atom at byte {} is {} bytes long", end, size))?;
+        let known = [
+            b"ftyp", b"mdat", b"moov", b"free", b"skip", b"udta", b"wide",
+        ];
+        if size < 8 || next > bytes.len() || !known.iter().any(|t| &bytes[end + 4..end + 8] == *t) {
+            break;
+        }
+        end = next;
+    }
+    bytes.truncate(end);
+    Ok(())
+}
+
+impl Pcm {
+    /// Keeps the samples between `start` and `end` seconds.
+    pub fn slice(&mut self, start: f32, end: Option<f32>) {
+        let frame = |time: f32| (time * self.rate as f32) as usize * self.channels as usize;
+        let len = self.samples.len();
+        let end = end.map_or(len, |end| frame(end).min(len));
+        let start = frame(start).min(end);
+        self.samples = self.samples[start..end].to_vec();
+    }
+
+    /// 16-bit PCM wav bytes, which is what the mixer reads.
+    pub fn to_wav(&self) -> Vec<u8> {
+        let data = self.samples.len() as u32 * 2;
+        let mut bytes = Vec::with_capacity(44 + data as usize);
+        bytes.extend_from_slice(b"RIFF");
+        bytes.extend_from_slice(&(36 + data).to_le_bytes());
+        bytes.extend_from_slice(b"WAVEfmt ");
+        bytes.extend_from_slice(&16u32.to_le_bytes());
+        bytes.extend_from_slice(&1u16.to_le_bytes());
+        bytes.extend_from_slice(&self.channels.to_le_bytes());
+        bytes.extend_from_slice(&self.rate.to_le_bytes());
+        bytes.extend_from_slice(&(self.rate * self.channels as u32 * 2).to_le_bytes());
+        bytes.extend_from_slice(&(self.channels * 2).to_le_bytes());
+        bytes.extend_from_slice(&16u16.to_le_bytes());
+        bytes.extend_from_slice(b"data");
+        bytes.extend_from_slice(&data.to_le_bytes());
+        for sample in &self.samples {
+            bytes.extend_from_slice(&sample.to_le_bytes());
+        }
+        bytes
+    }
+}
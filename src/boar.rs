@@ -0,0 +1,214 @@
+use macroquad::prelude::rand;
+
+use crate::animation::*;
+use crate::assets::{Assets, Defs};
+use crate::camera::Camera;
+use crate::collision::Aabb;
+use crate::enemies::{Enemy, Senses};
+use crate::health::Health;
+use crate::map::*;
+use crate::vecs::*;
+
+/// How close the player has to come before a boar charges.
+const SIGHT: f32 = 550.;
+/// Seconds it paws the ground before charging, so the player can dodge.
+const WINDUP: f32 = 0.7;
+/// Pixels per second.
+const CHARGE_SPEED: f32 = 900.;
+/// Seconds a charge lasts if it doesn't hit a wall first.
+const CHARGE_TIME: f32 = 1.2;
+/// Seconds it stays dazed after running into a wall.
+const STUN: f32 = 1.5;
+/// Seconds it catches its breath after a charge that hit nothing.
+const REST: f32 = 0.8;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Charge {
+    Idle,
+    WindUp,
+    Charging,
+    Stunned,
+}
+
+/// A ground enemy that winds up, charges in a straight line and knocks
+/// itself out on walls.
+pub struct Boar {
+    pub pos: Vec2,
+    pub size: f32,
+    pub sprite: AnimatedSprite,
+    /// Dust kicked up behind it while charging.
+    pub dust: AnimatedSprite,
+    pub flipped: bool,
+    pub hit: Aabb,
+    pub health: Health,
+    pub knockback: Vec2,
+    pub charge: Charge,
+    /// Seconds left in the current state.
+    pub time: f32,
+    /// Where the charge is headed, fixed when the wind-up starts.
+    pub dir: Vec2,
+}
+
+impl Boar {
+    pub fn new(pos: Vec2, defs: &Defs, size: f32) -> Self {
+        let sprite = AnimatedSprite::from_def(defs.animation("boar"), defs);
+        let dust = AnimatedSprite::from_def(defs.animation("boar_dust"), defs);
+        let idle = &sprite.animations[0];
+        let hit = Aabb::new(
+            pos,
+            Vec2::new(idle.width as f32 * size, idle.height as f32 * size),
+        );
+
+        Self {
+            pos,
+            size,
+            sprite,
+            dust,
+            flipped: false,
+            hit,
+            health: Health::new(4, 0.3),
+            knockback: Vec2::new(0., 0.),
+            charge: Charge::Idle,
+            time: 0.,
+            dir: Vec2::new(0., 0.),
+        }
+    }
+}
+
+impl Enemy for Boar {
+    fn update(&mut self, dt: f32, senses: &mut Senses) {
+        let (walls, floors, player) = (senses.walls, senses.floors, senses.player);
+        self.health.update(dt);
+        self.time -= dt;
+        let center = self.hit.center();
+        let (dx, dy) = (player.x - center.x, player.y - center.y);
+
+        let mut speed = 0.;
+        match self.charge {
+            Charge::Idle => {
+                if self.time <= 0. && dx * dx + dy * dy < SIGHT * SIGHT {
+                    self.charge = Charge::WindUp;
+                    self.time = WINDUP;
+                    self.dir = Vec2::new(dx, dy).normalize();
+                    self.flipped = dx < 0.;
+                }
+            }
+            Charge::WindUp => {
+                if self.time <= 0. {
+                    self.charge = Charge::Charging;
+                    self.time = CHARGE_TIME;
+                    self.dust.restart();
+                }
+            }
+            Charge::Charging => {
+                speed = CHARGE_SPEED;
+                if self.time <= 0. {
+                    self.charge = Charge::Idle;
+                    self.time = REST;
+                }
+            }
+            Charge::Stunned => {
+                if self.time <= 0. {
+                    self.charge = Charge::Idle;
+                }
+            }
+        }
+        self.sprite.play(match self.charge {
+            Charge::WindUp | Charge::Charging => "charge",
+            Charge::Idle | Charge::Stunned => "idle",
+        });
+
+        //collision
+        let body = self.hit;
+        let dx = self.dir.x * speed * dt + self.knockback.x * dt;
+        let dy = self.dir.y * speed * dt + self.knockback.y * dt;
+        let moved = walls.move_box(&body, Vec2::new(dx, dy));
+        let stopped = floors.move_box(&body, moved.delta);
+        self.pos.x += stopped.delta.x;
+        self.pos.y += stopped.delta.y;
+
+        //running into a wall or water knocks it out
+        if self.charge == Charge::Charging
+            && (!moved.touched.is_empty() || !stopped.touched.is_empty())
+        {
+            self.charge = Charge::Stunned;
+            self.time = STUN;
+        }
+
+        let decay = (1. - dt * 8.).max(0.);
+        self.knockback = Vec2::new(self.knockback.x * decay, self.knockback.y * decay);
+        self.sprite.update(dt);
+        self.dust.update(dt);
+        let animation = &self.sprite.animations[self.sprite.cur_animation];
+        self.hit = Aabb::new(
+            self.pos,
+            Vec2::new(
+                animation.width as f32 * self.size,
+                animation.height as f32 * self.size,
+            ),
+        );
+    }
+
+    fn draw(&self, assets: &Assets, camera: &Camera, walls: &Map) {
+        let size = self.size * self.sprite.animations[self.sprite.cur_animation].height as f32;
+        //draw walls close to the boar
+        walls.draw_near(&self.pos, size, false, assets, camera);
+
+        if self.charge == Charge::Charging {
+            let behind = Vec2::new(
+                if self.flipped {
+                    self.hit.pos.x + self.hit.size.x
+                } else {
+                    self.hit.pos.x - self.dust.animations[0].width as f32 * self.size
+                },
+                self.hit.pos.y + self.hit.size.y / 3.,
+            );
+            self.dust
+                .draw(&behind, &self.size, &self.flipped, assets, camera);
+        }
+
+        //shakes while winding up or dazed
+        let mut pos = self.pos;
+        if matches!(self.charge, Charge::WindUp | Charge::Stunned) {
+            pos.x += rand::gen_range(-3., 3.);
+        }
+        //flash while hurt
+        if self.health.invulnerable == 0. || (self.health.invulnerable * 20.) as i32 % 2 == 1 {
+            self.sprite
+                .draw(&pos, &self.size, &self.flipped, assets, camera);
+        }
+
+        //draw walls close to the boar
+        walls.draw_near(&self.pos, size, true, assets, camera);
+    }
+
+    fn hitbox(&self) -> Aabb {
+        self.hit
+    }
+
+    fn health(&self) -> &Health {
+        &self.health
+    }
+
+    /// Gets pushed away from the hit, unless charging straight through it.
+    fn take_damage(&mut self, amount: i32, from: Vec2) -> bool {
+        if !self.health.damage(amount) {
+            return false;
+        }
+        if self.charge != Charge::Charging || self.health.is_dead() {
+            let center = self.hit.center();
+            let away = Vec2::new(center.x - from.x, center.y - from.y).normalize();
+            self.knockback = Vec2::new(away.x * 400., away.y * 400.);
+        }
+        true
+    }
+
+    /// Hurts on touch, far more while charging.
+    fn touch(&mut self) -> Option<i32> {
+        Some(if self.charge == Charge::Charging {
+            2
+        } else {
+            1
+        })
+    }
+}
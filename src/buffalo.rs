@@ -0,0 +1,245 @@
+use crate::animation::*;
+use crate::assets::{Assets, Defs};
+use crate::camera::Camera;
+use crate::collision::Aabb;
+use crate::enemies::{Enemy, Senses};
+use crate::health::Health;
+use crate::map::*;
+use crate::vecs::*;
+
+/// Phases the fight goes through as the buffalo loses health.
+pub const PHASES: i32 = 3;
+/// Seconds a charge lasts if it doesn't hit a wall first.
+const CHARGE_TIME: f32 = 1.4;
+/// Seconds it stands still between runs of charges, open to attack.
+const REST: f32 = 1.6;
+/// Seconds it flinches when hit outside a charge.
+const FLINCH: f32 = 0.4;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Stampede {
+    /// Waits to be woken by the player walking into its arena.
+    Asleep,
+    Idle,
+    /// Rears up before a charge; the charge starts on the clip's `charge`
+    /// marker.
+    WindUp,
+    Charging,
+    /// Slides to a halt at the end of a charge.
+    Skid,
+    Hurt,
+}
+
+/// The mini-boss: charges across its arena, more often and faster as it
+/// gets weaker.
+pub struct Buffalo {
+    pub pos: Vec2,
+    pub size: f32,
+    pub sprite: AnimatedSprite,
+    pub flipped: bool,
+    pub hit: Aabb,
+    pub health: Health,
+    pub stampede: Stampede,
+    /// Seconds left in the current state.
+    pub time: f32,
+    pub dir: Vec2,
+    /// Pixels per second it is moving along `dir`, so skids slow down.
+    pub speed: f32,
+    /// Charges left before it rests.
+    pub charges: i32,
+}
+
+impl Buffalo {
+    pub fn new(pos: Vec2, defs: &Defs, size: f32) -> Self {
+        let sprite = AnimatedSprite::from_def(defs.animation("buffalo"), defs);
+        let idle = &sprite.animations[0];
+        let hit = Aabb::new(
+            pos,
+            Vec2::new(idle.width as f32 * size, idle.height as f32 * size),
+        );
+
+        Self {
+            pos,
+            size,
+            sprite,
+            flipped: false,
+            hit,
+            health: Health::new(15, 0.3),
+            stampede: Stampede::Asleep,
+            time: 0.,
+            dir: Vec2::new(0., 0.),
+            speed: 0.,
+            charges: 0,
+        }
+    }
+
+    /// 1 at full health, up to `PHASES` near death.
+    fn phase(&self) -> i32 {
+        let lost = self.health.max - self.health.hp;
+        (1 + lost * PHASES / self.health.max).min(PHASES)
+    }
+
+    fn wind_up(&mut self, player: Vec2) {
+        let center = self.hit.center();
+        self.dir = Vec2::new(player.x - center.x, player.y - center.y).normalize();
+        self.flipped = self.dir.x < 0.;
+        self.stampede = Stampede::WindUp;
+        self.sprite.play("charge");
+        self.sprite.restart();
+    }
+
+    fn skid(&mut self, sounds: &mut Vec<String>) {
+        self.stampede = Stampede::Skid;
+        self.sprite.play("skid");
+        self.sprite.restart();
+        sounds.push("buffalo_stop".to_string());
+    }
+}
+
+impl Enemy for Buffalo {
+    fn update(&mut self, dt: f32, senses: &mut Senses) {
+        let (walls, floors, player) = (senses.walls, senses.floors, senses.player);
+        let sounds = &mut *senses.sounds;
+        self.health.update(dt);
+        self.sprite.update(dt);
+        self.time -= dt;
+
+        if !self.health.is_dead() {
+            match self.stampede {
+                Stampede::Asleep => (),
+                Stampede::Idle => {
+                    self.sprite.play("idle");
+                    if self.time <= 0. {
+                        self.charges = self.phase();
+                        self.wind_up(player);
+                    }
+                }
+                Stampede::WindUp => {
+                    if self.sprite.fired("charge") {
+                        self.stampede = Stampede::Charging;
+                        self.time = CHARGE_TIME;
+                        self.speed = 600. + 200. * self.phase() as f32;
+                        self.charges -= 1;
+                    }
+                }
+                Stampede::Charging => {
+                    if self.time <= 0. {
+                        self.skid(sounds);
+                    }
+                }
+                Stampede::Skid => {
+                    self.speed = (self.speed - 2000. * dt).max(0.);
+                    if self.sprite.finished("skid") {
+                        self.speed = 0.;
+                        if self.charges > 0 {
+                            self.wind_up(player);
+                        } else {
+                            self.stampede = Stampede::Idle;
+                            self.time = REST;
+                        }
+                    }
+                }
+                Stampede::Hurt => {
+                    if self.time <= 0. {
+                        self.stampede = Stampede::Idle;
+                    }
+                }
+            }
+        }
+
+        //collision
+        let delta = Vec2::new(self.dir.x * self.speed * dt, self.dir.y * self.speed * dt);
+        let moved = walls.move_box(&self.hit, delta);
+        let stopped = floors.move_box(&self.hit, moved.delta);
+        self.pos.x += stopped.delta.x;
+        self.pos.y += stopped.delta.y;
+        if !moved.touched.is_empty() || !stopped.touched.is_empty() {
+            self.speed = 0.;
+            if self.stampede == Stampede::Charging {
+                self.skid(sounds);
+            }
+        }
+
+        let animation = &self.sprite.animations[self.sprite.cur_animation];
+        self.hit = Aabb::new(
+            self.pos,
+            Vec2::new(
+                animation.width as f32 * self.size,
+                animation.height as f32 * self.size,
+            ),
+        );
+    }
+
+    fn draw(&self, assets: &Assets, camera: &Camera, walls: &Map) {
+        let size = self.size * self.sprite.animations[self.sprite.cur_animation].height as f32;
+        //draw walls close to the buffalo
+        walls.draw_near(&self.pos, size, false, assets, camera);
+
+        //flash while hurt
+        if self.health.invulnerable == 0. || (self.health.invulnerable * 20.) as i32 % 2 == 1 {
+            self.sprite
+                .draw(&self.pos, &self.size, &self.flipped, assets, camera);
+        }
+
+        //draw walls close to the buffalo
+        walls.draw_near(&self.pos, size, true, assets, camera);
+    }
+
+    fn hitbox(&self) -> Aabb {
+        self.hit
+    }
+
+    fn health(&self) -> &Health {
+        &self.health
+    }
+
+    /// Only flinches when not charging, and isn't pushed around at all.
+    fn take_damage(&mut self, amount: i32, _from: Vec2) -> bool {
+        if !self.health.damage(amount) {
+            return false;
+        }
+        self.wake();
+        if self.health.is_dead() {
+            //a charge stops where it falls
+            self.speed = 0.;
+            self.sprite.play("die");
+            self.sprite.restart();
+        } else if matches!(self.stampede, Stampede::Idle | Stampede::Hurt) {
+            self.stampede = Stampede::Hurt;
+            self.time = FLINCH;
+            self.speed = 0.;
+            self.sprite.play("hurt");
+            self.sprite.restart();
+        }
+        true
+    }
+
+    /// Done once the death animation has played out.
+    fn is_gone(&self) -> bool {
+        self.health.is_dead() && !self.sprite.playing
+    }
+
+    fn hurt_sound(&self) -> Option<&'static str> {
+        Some("buffalo_hurt")
+    }
+
+    /// Hurts on touch, badly while charging.
+    fn touch(&mut self) -> Option<i32> {
+        Some(if self.stampede == Stampede::Charging {
+            3
+        } else {
+            1
+        })
+    }
+
+    fn boss(&self) -> bool {
+        true
+    }
+
+    fn wake(&mut self) {
+        if self.stampede == Stampede::Asleep {
+            self.stampede = Stampede::Idle;
+            self.time = REST / 2.;
+        }
+    }
+}
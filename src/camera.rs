@@ -1,6 +1,7 @@
+use crate::input::Input;
 use crate::player::Player;
 use crate::vecs::Vec2;
-use macroquad::prelude::{get_frame_time, screen_height, screen_width};
+use macroquad::prelude::{screen_height, screen_width};
 
 pub struct Camera {
     pub pos: Vec2,
@@ -10,16 +11,11 @@ pub struct Camera {
 }
 
 impl Camera {
-    pub fn update(&mut self, player: &Player) {
-        let mut speed = player.speed * get_frame_time() * 60.;
+    pub fn update(&mut self, dt: f32, player: &Player) {
+        let speed = player.speed * dt * 60.;
 
-        //fix double speed when moving diagonally
-        if player.dir[0].abs() > 0 && player.dir[1].abs() > 0 {
-            speed /= 1.5;
-        }
-
-        self.speed.x -= speed * player.dir[0] as f32 / 2.;
-        self.speed.y -= speed * player.dir[1] as f32 / 2.;
+        self.speed.x -= speed * player.dir.x / 2.;
+        self.speed.y -= speed * player.dir.y / 2.;
 
         if self.speed.x.abs() > self.speed_limit.x {
             let mut mult = (self.speed.x > 0.) as i8;
@@ -36,24 +32,24 @@ impl Camera {
             self.speed.y = self.speed_limit.y * mult as f32;
         }
 
-        if player.dir[0] == 0 && self.speed.x > 0. {
+        if player.dir.x == 0. && self.speed.x > 0. {
             self.speed.x -= speed / 3.;
             if self.speed.x < 0. {
                 self.speed.x = 0.;
             }
-        } else if player.dir[0] == 0 && self.speed.x < 0. {
+        } else if player.dir.x == 0. && self.speed.x < 0. {
             self.speed.x += speed / 3.;
             if self.speed.x > 0. {
                 self.speed.x = 0.;
             }
         }
 
-        if player.dir[1] == 0 && self.speed.y > 0. {
+        if player.dir.y == 0. && self.speed.y > 0. {
             self.speed.y -= speed / 3.;
             if self.speed.y < 0. {
                 self.speed.y = 0.;
             }
-        } else if player.dir[1] == 0 && self.speed.y < 0. {
+        } else if player.dir.y == 0. && self.speed.y < 0. {
             self.speed.y += speed / 3.;
             if self.speed.y > 0. {
                 self.speed.y = 0.;
@@ -65,4 +61,11 @@ impl Camera {
             (-player.pos.y - player.real_size[1] / 2.) + screen_height() / 2. + self.speed.y,
         );
     }
+
+    pub fn pan(&mut self, input: &Input) {
+        if input.pan {
+            self.pos.x -= input.x * 4.;
+            self.pos.y -= input.y * 4.;
+        }
+    }
 }
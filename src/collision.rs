@@ -0,0 +1,290 @@
+use std::collections::HashMap;
+
+use crate::vecs::Vec2;
+
+/// An axis aligned box. Boxes that only share an edge don't overlap, and a
+/// box with no width or height contains no point.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub pos: Vec2,
+    pub size: Vec2,
+}
+
+impl Aabb {
+    pub fn new(pos: Vec2, size: Vec2) -> Self {
+        Self { pos, size }
+    }
+
+    pub fn center(&self) -> Vec2 {
+        Vec2::new(self.pos.x + self.size.x / 2., self.pos.y + self.size.y / 2.)
+    }
+
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        self.pos.x < other.pos.x + other.size.x
+            && other.pos.x < self.pos.x + self.size.x
+            && self.pos.y < other.pos.y + other.size.y
+            && other.pos.y < self.pos.y + self.size.y
+    }
+
+    /// Includes the top and left edges but not the bottom and right ones, so
+    /// boxes laid side by side never both contain a point.
+    pub fn contains(&self, point: Vec2) -> bool {
+        point.x >= self.pos.x
+            && point.x < self.pos.x + self.size.x
+            && point.y >= self.pos.y
+            && point.y < self.pos.y + self.size.y
+    }
+
+    /// The shortest move that takes this box out of `other`, along a single
+    /// axis, or `None` if they don't overlap.
+    pub fn overlap(&self, other: &Aabb) -> Option<Vec2> {
+        if !self.intersects(other) {
+            return None;
+        }
+        let shortest = |back: f32, forward: f32| {
+            if -back < forward {
+                back
+            } else {
+                forward
+            }
+        };
+        let x = shortest(
+            other.pos.x - (self.pos.x + self.size.x),
+            other.pos.x + other.size.x - self.pos.x,
+        );
+        let y = shortest(
+            other.pos.y - (self.pos.y + self.size.y),
+            other.pos.y + other.size.y - self.pos.y,
+        );
+        Some(if x.abs() <= y.abs() {
+            Vec2::new(x, 0.)
+        } else {
+            Vec2::new(0., y)
+        })
+    }
+
+    /// How far along `delta`, from 0 to 1, this box can move before it
+    /// overlaps `other`, or `None` if it never does. Sliding along an edge
+    /// isn't a hit; starting inside `other` is a hit at 0.
+    pub fn sweep(&self, delta: Vec2, other: &Aabb) -> Option<f32> {
+        let axis = |pos: f32, size: f32, delta: f32, other_pos: f32, other_size: f32| {
+            if delta == 0. {
+                if pos < other_pos + other_size && other_pos < pos + size {
+                    Some((f32::NEG_INFINITY, f32::INFINITY))
+                } else {
+                    None
+                }
+            } else {
+                let a = (other_pos - (pos + size)) / delta;
+                let b = (other_pos + other_size - pos) / delta;
+                Some((a.min(b), a.max(b)))
+            }
+        };
+        let (x_entry, x_exit) = axis(self.pos.x, self.size.x, delta.x, other.pos.x, other.size.x)?;
+        let (y_entry, y_exit) = axis(self.pos.y, self.size.y, delta.y, other.pos.y, other.size.y)?;
+
+        let entry = x_entry.max(y_entry);
+        let exit = x_exit.min(y_exit);
+        if entry >= exit || entry > 1. || exit <= 0. {
+            return None;
+        }
+        Some(entry.max(0.))
+    }
+}
+
+/// A round collider, for projectiles.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Circle {
+    pub center: Vec2,
+    pub radius: f32,
+}
+
+impl Circle {
+    pub fn new(center: Vec2, radius: f32) -> Self {
+        Self { center, radius }
+    }
+
+    /// Like boxes, a circle only touching `aabb` doesn't overlap it.
+    pub fn intersects(&self, aabb: &Aabb) -> bool {
+        let x = self.center.x.clamp(aabb.pos.x, aabb.pos.x + aabb.size.x);
+        let y = self.center.y.clamp(aabb.pos.y, aabb.pos.y + aabb.size.y);
+        let (dx, dy) = (self.center.x - x, self.center.y - y);
+        dx * dx + dy * dy < self.radius * self.radius
+    }
+
+    pub fn bounds(&self) -> Aabb {
+        Aabb::new(
+            Vec2::new(self.center.x - self.radius, self.center.y - self.radius),
+            Vec2::new(self.radius * 2., self.radius * 2.),
+        )
+    }
+}
+
+/// Broadphase that buckets boxes into a grid of `cell` sized squares, so a
+/// query only looks at what is nearby. Rebuilt every frame.
+pub struct SpatialHash<T> {
+    pub cell: f32,
+    cells: HashMap<(i32, i32), Vec<(T, Aabb)>>,
+}
+
+impl<T: Copy + PartialEq> SpatialHash<T> {
+    pub fn new(cell: f32) -> Self {
+        Self {
+            cell,
+            cells: HashMap::new(),
+        }
+    }
+
+    fn span(&self, aabb: &Aabb) -> (i32, i32, i32, i32) {
+        let cell = |value: f32| (value / self.cell).floor() as i32;
+        (
+            cell(aabb.pos.x),
+            cell(aabb.pos.y),
+            cell(aabb.pos.x + aabb.size.x),
+            cell(aabb.pos.y + aabb.size.y),
+        )
+    }
+
+    pub fn insert(&mut self, item: T, aabb: Aabb) {
+        let (x1, y1, x2, y2) = self.span(&aabb);
+        for y in y1..=y2 {
+            for x in x1..=x2 {
+                self.cells.entry((x, y)).or_default().push((item, aabb));
+            }
+        }
+    }
+
+    /// Every item whose box overlaps `aabb`, each listed once.
+    pub fn query(&self, aabb: &Aabb) -> Vec<T> {
+        let (x1, y1, x2, y2) = self.span(aabb);
+        let mut found = Vec::new();
+        for y in y1..=y2 {
+            for x in x1..=x2 {
+                for (item, other) in self.cells.get(&(x, y)).into_iter().flatten() {
+                    if other.intersects(aabb) && !found.contains(item) {
+                        found.push(*item);
+                    }
+                }
+            }
+        }
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn aabb(x: f32, y: f32, w: f32, h: f32) -> Aabb {
+        Aabb::new(Vec2::new(x, y), Vec2::new(w, h))
+    }
+
+    #[test]
+    fn overlapping_boxes_intersect() {
+        assert!(aabb(0., 0., 10., 10.).intersects(&aabb(5., 5., 10., 10.)));
+        assert!(aabb(0., 0., 10., 10.).intersects(&aabb(2., 2., 2., 2.)));
+    }
+
+    #[test]
+    fn touching_edges_dont_intersect() {
+        let a = aabb(0., 0., 10., 10.);
+        assert!(!a.intersects(&aabb(10., 0., 10., 10.)));
+        assert!(!a.intersects(&aabb(0., 10., 10., 10.)));
+        assert!(!a.intersects(&aabb(10., 10., 10., 10.)));
+        assert!(!a.intersects(&aabb(-10., 0., 10., 10.)));
+    }
+
+    #[test]
+    fn zero_size_boxes() {
+        let a = aabb(0., 0., 10., 10.);
+        let point = aabb(5., 5., 0., 0.);
+        assert!(a.intersects(&point));
+        assert!(!a.intersects(&aabb(10., 5., 0., 0.)));
+        assert!(!point.intersects(&point));
+        assert!(!point.contains(Vec2::new(5., 5.)));
+        assert_eq!(point.overlap(&point), None);
+    }
+
+    #[test]
+    fn contains_is_half_open() {
+        let a = aabb(0., 0., 10., 10.);
+        assert!(a.contains(Vec2::new(0., 0.)));
+        assert!(a.contains(Vec2::new(9.9, 9.9)));
+        assert!(!a.contains(Vec2::new(10., 5.)));
+        assert!(!a.contains(Vec2::new(5., 10.)));
+    }
+
+    #[test]
+    fn overlap_takes_the_shallow_axis() {
+        let a = aabb(0., 0., 10., 10.);
+        assert_eq!(a.overlap(&aabb(8., 1., 10., 10.)), Some(Vec2::new(-2., 0.)));
+        assert_eq!(a.overlap(&aabb(1., -7., 10., 10.)), Some(Vec2::new(0., 3.)));
+        assert_eq!(a.overlap(&aabb(10., 0., 10., 10.)), None);
+    }
+
+    #[test]
+    fn sweep_finds_the_first_contact() {
+        let a = aabb(0., 0., 10., 10.);
+        let wall = aabb(20., 0., 10., 10.);
+        assert_eq!(a.sweep(Vec2::new(20., 0.), &wall), Some(0.5));
+        assert_eq!(a.sweep(Vec2::new(5., 0.), &wall), None);
+        assert_eq!(a.sweep(Vec2::new(-20., 0.), &wall), None);
+        //fast enough to pass through in a single step
+        assert_eq!(a.sweep(Vec2::new(100., 0.), &wall), Some(0.1));
+    }
+
+    #[test]
+    fn sweep_along_an_edge_is_not_a_hit() {
+        let a = aabb(0., 0., 10., 10.);
+        assert_eq!(a.sweep(Vec2::new(30., 0.), &aabb(15., 10., 10., 10.)), None);
+        assert_eq!(
+            a.sweep(Vec2::new(10., 0.), &aabb(10., 0., 10., 10.)),
+            Some(0.)
+        );
+        assert_eq!(
+            a.sweep(Vec2::new(0., 0.), &aabb(5., 5., 10., 10.)),
+            Some(0.)
+        );
+        assert_eq!(a.sweep(Vec2::new(0., 0.), &aabb(10., 0., 10., 10.)), None);
+    }
+
+    #[test]
+    fn sweep_with_a_zero_size_box() {
+        let point = aabb(0., 5., 0., 0.);
+        let wall = aabb(10., 0., 10., 10.);
+        assert_eq!(point.sweep(Vec2::new(20., 0.), &wall), Some(0.5));
+        assert_eq!(
+            point.sweep(Vec2::new(20., 0.), &aabb(10., 5., 0., 0.)),
+            None
+        );
+    }
+
+    #[test]
+    fn circle_against_box() {
+        let a = aabb(0., 0., 10., 10.);
+        assert!(Circle::new(Vec2::new(5., 5.), 1.).intersects(&a));
+        assert!(Circle::new(Vec2::new(12., 5.), 3.).intersects(&a));
+        assert!(!Circle::new(Vec2::new(13., 5.), 3.).intersects(&a));
+        //near a corner the distance is diagonal
+        assert!(!Circle::new(Vec2::new(12., 12.), 2.5).intersects(&a));
+        assert!(!Circle::new(Vec2::new(5., 5.), 0.).intersects(&a));
+        assert_eq!(
+            Circle::new(Vec2::new(5., 5.), 2.).bounds(),
+            aabb(3., 3., 4., 4.)
+        );
+    }
+
+    #[test]
+    fn spatial_hash_queries_nearby_items() {
+        let mut hash = SpatialHash::new(10.);
+        hash.insert(1, aabb(0., 0., 5., 5.));
+        hash.insert(2, aabb(8., 8., 25., 5.));
+        hash.insert(3, aabb(100., 100., 5., 5.));
+        assert_eq!(hash.query(&aabb(2., 2., 1., 1.)), vec![1]);
+        assert_eq!(hash.query(&aabb(4., 4., 30., 30.)), vec![1, 2]);
+        assert_eq!(hash.query(&aabb(25., 9., 1., 1.)), vec![2]);
+        //touching isn't overlapping, even when sharing a cell
+        assert!(hash.query(&aabb(5., 0., 2., 2.)).is_empty());
+        assert!(hash.query(&aabb(-50., -50., 10., 10.)).is_empty());
+    }
+}
@@ -0,0 +1,94 @@
+use macroquad::prelude::{clear_background, draw_text, BLACK, GRAY, RED, WHITE, YELLOW};
+
+use crate::input::{Action, Binding, BINDINGS_PATH};
+use crate::scene::{Context, Scene, Transition};
+
+/// Lists every action with its bindings and lets the player rebind them.
+pub struct Controls {
+    pub cur: usize,
+    pub waiting: bool,
+    /// Why the bindings couldn't be saved, shown until the player leaves
+    /// without them.
+    pub error: Option<String>,
+}
+
+impl Controls {
+    pub fn new() -> Self {
+        Self {
+            cur: 0,
+            waiting: false,
+            error: None,
+        }
+    }
+}
+
+impl Scene for Controls {
+    fn update(&mut self, ctx: &mut Context, _dt: f32) -> Transition {
+        let bindings = &mut ctx.bindings;
+        if self.waiting {
+            //Back cancels instead of being bound
+            if bindings.pressed(Action::Back) {
+                self.waiting = false;
+                ctx.audio.play("cancel");
+            } else if bindings.capture(Action::ALL[self.cur]) {
+                self.waiting = false;
+            }
+        } else if bindings.pressed(Action::MoveUp) {
+            self.cur = (self.cur + Action::ALL.len() - 1) % Action::ALL.len();
+            ctx.audio.play("button");
+        } else if bindings.pressed(Action::MoveDown) {
+            self.cur = (self.cur + 1) % Action::ALL.len();
+            ctx.audio.play("button");
+        } else if bindings.pressed(Action::Confirm) {
+            self.waiting = true;
+            ctx.audio.play("confirm");
+        } else if bindings.pressed(Action::Back) {
+            ctx.audio.play("cancel");
+            if self.error.is_none() {
+                if let Err(err) = bindings.to_file(BINDINGS_PATH) {
+                    self.error = Some(format!("Could not save the bindings: {}", err));
+                    return Transition::None;
+                }
+            }
+            return Transition::Pop;
+        }
+        Transition::None
+    }
+
+    fn draw(&self, ctx: &Context) {
+        clear_background(BLACK);
+        draw_text("Controls", 40., 50., 40., WHITE);
+
+        for (i, action) in Action::ALL.iter().enumerate() {
+            let keys: Vec<String> = ctx
+                .bindings
+                .get(*action)
+                .iter()
+                .map(Binding::name)
+                .collect();
+            let keys = if self.waiting && i == self.cur {
+                "press a key, or Back to cancel...".to_string()
+            } else {
+                keys.join(", ")
+            };
+            let color = if i == self.cur { YELLOW } else { WHITE };
+            let y = 90. + i as f32 * 26.;
+            draw_text(&action.name(), 40., y, 26., color);
+            draw_text(&keys, 300., y, 26., color);
+        }
+        let y = 100. + Action::ALL.len() as f32 * 26.;
+        match &self.error {
+            Some(error) => {
+                draw_text(error, 40., y, 22., RED);
+                draw_text("Back: return without saving", 40., y + 24., 22., GRAY);
+            }
+            None => draw_text(
+                "Confirm: rebind    Back: save and return",
+                40.,
+                y,
+                22.,
+                GRAY,
+            ),
+        }
+    }
+}
@@ -0,0 +1,226 @@
+use crate::animation::*;
+use crate::assets::{Assets, Defs};
+use crate::camera::Camera;
+use crate::collision::Aabb;
+use crate::enemies::{Enemy, Senses};
+use crate::health::Health;
+use crate::map::Map;
+use crate::vecs::*;
+
+/// How close the player has to come before a crow starts hunting.
+const SIGHT: f32 = 700.;
+/// How far above and around the player a crow circles between dives.
+const ORBIT: f32 = 260.;
+/// Seconds circling before each dive.
+const CIRCLE_TIME: f32 = 1.8;
+const DIVE_SPEED: f32 = 900.;
+/// Seconds a dive lasts at most, so a miss doesn't carry it off forever.
+const DIVE_TIME: f32 = 0.6;
+const RETREAT_SPEED: f32 = 450.;
+const RETREAT_TIME: f32 = 0.8;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Swoop {
+    /// Flaps around its home or the player, waiting to dive.
+    Circle,
+    /// Flies straight at where the player was when it started.
+    Dive,
+    /// Pulls back up after a dive, hit or miss.
+    Retreat,
+}
+
+/// A flying enemy. It ignores walls and water, circling the player and diving
+/// at it.
+pub struct Crow {
+    pub pos: Vec2,
+    pub size: f32,
+    pub sprite: AnimatedSprite,
+    pub flipped: bool,
+    pub hit: Aabb,
+    pub health: Health,
+    pub knockback: Vec2,
+    pub swoop: Swoop,
+    /// Seconds in the current swoop.
+    pub time: f32,
+    /// Where the dive is headed, or where to retreat from.
+    pub target: Vec2,
+    /// Where it waits while the player is out of sight.
+    pub home: Vec2,
+    /// Angle around the player while circling.
+    pub angle: f32,
+}
+
+impl Crow {
+    pub fn new(pos: Vec2, defs: &Defs, size: f32) -> Self {
+        let sprite = AnimatedSprite::from_def(defs.animation("crow"), defs);
+        let idle = &sprite.animations[0];
+        let hit = Aabb::new(
+            pos,
+            Vec2::new(idle.width as f32 * size, idle.height as f32 * size),
+        );
+
+        Self {
+            pos,
+            size,
+            sprite,
+            flipped: false,
+            hit,
+            health: Health::new(2, 0.3),
+            knockback: Vec2::new(0., 0.),
+            swoop: Swoop::Circle,
+            time: 0.,
+            target: pos,
+            home: pos,
+            angle: 0.,
+        }
+    }
+
+    /// Flies back up, away from where it was diving.
+    fn retreat(&mut self) {
+        self.swoop = Swoop::Retreat;
+        self.time = 0.;
+        self.target = self.hit.center();
+    }
+
+    /// Moves towards `to` at `speed` pixels per second, returning true once
+    /// there.
+    fn fly(&mut self, dt: f32, to: Vec2, speed: f32) -> bool {
+        let center = self.hit.center();
+        let (dx, dy) = (to.x - center.x, to.y - center.y);
+        let distance = (dx * dx + dy * dy).sqrt();
+        let step = speed * dt;
+        if distance <= step {
+            self.pos.x += dx;
+            self.pos.y += dy;
+            return true;
+        }
+        self.pos.x += dx / distance * step;
+        self.pos.y += dy / distance * step;
+        if dx != 0. {
+            self.flipped = dx < 0.;
+        }
+        false
+    }
+}
+
+impl Enemy for Crow {
+    fn update(&mut self, dt: f32, senses: &mut Senses) {
+        let player = senses.player;
+        self.health.update(dt);
+        self.time += dt;
+        let center = self.hit.center();
+        let seen = (player.x - center.x).powi(2) + (player.y - center.y).powi(2) < SIGHT * SIGHT;
+
+        //stunned while hurt, only the knockback moves it
+        let stunned = self.health.invulnerable > 0. || self.health.is_dead();
+        if !stunned {
+            match self.swoop {
+                Swoop::Circle => {
+                    self.angle += dt * 2.;
+                    let around = if seen { player } else { self.home };
+                    let to = Vec2::new(
+                        around.x + self.angle.cos() * ORBIT,
+                        around.y - ORBIT / 2. + self.angle.sin() * ORBIT / 3.,
+                    );
+                    self.fly(dt, to, RETREAT_SPEED);
+                    if seen && self.time > CIRCLE_TIME {
+                        self.swoop = Swoop::Dive;
+                        self.time = 0.;
+                        self.target = player;
+                    }
+                }
+                Swoop::Dive => {
+                    if self.fly(dt, self.target, DIVE_SPEED) || self.time > DIVE_TIME {
+                        self.retreat();
+                    }
+                }
+                Swoop::Retreat => {
+                    //straight up and away from the player
+                    let side = if center.x < player.x { -1. } else { 1. };
+                    let to = Vec2::new(self.target.x + side * ORBIT, self.target.y - ORBIT);
+                    if self.fly(dt, to, RETREAT_SPEED) || self.time > RETREAT_TIME {
+                        self.swoop = Swoop::Circle;
+                        self.time = 0.;
+                        self.angle = (center.y - player.y).atan2(center.x - player.x);
+                    }
+                }
+            }
+            self.sprite.play(if self.swoop == Swoop::Dive {
+                "dive"
+            } else {
+                "fly"
+            });
+        }
+
+        self.pos.x += self.knockback.x * dt;
+        self.pos.y += self.knockback.y * dt;
+        let decay = (1. - dt * 8.).max(0.);
+        self.knockback = Vec2::new(self.knockback.x * decay, self.knockback.y * decay);
+        self.sprite.update(dt);
+        let animation = &self.sprite.animations[self.sprite.cur_animation];
+        self.hit = Aabb::new(
+            self.pos,
+            Vec2::new(
+                animation.width as f32 * self.size,
+                animation.height as f32 * self.size,
+            ),
+        );
+    }
+
+    fn draw(&self, assets: &Assets, camera: &Camera, _walls: &Map) {
+        //flash while hurt
+        if self.health.invulnerable == 0. || (self.health.invulnerable * 20.) as i32 % 2 == 1 {
+            self.sprite
+                .draw(&self.pos, &self.size, &self.flipped, assets, camera);
+        }
+    }
+
+    fn hitbox(&self) -> Aabb {
+        self.hit
+    }
+
+    fn health(&self) -> &Health {
+        &self.health
+    }
+
+    /// Gets pushed away from the hit, breaking off any dive.
+    fn take_damage(&mut self, amount: i32, from: Vec2) -> bool {
+        if !self.health.damage(amount) {
+            return false;
+        }
+        let center = self.hit.center();
+        let away = Vec2::new(center.x - from.x, center.y - from.y).normalize();
+        self.knockback = Vec2::new(away.x * 500., away.y * 500.);
+        self.retreat();
+        self.sprite
+            .play(if self.health.is_dead() { "die" } else { "hurt" });
+        self.sprite.restart();
+        true
+    }
+
+    /// Done once the death animation has played out.
+    fn is_gone(&self) -> bool {
+        self.health.is_dead() && !self.sprite.playing
+    }
+
+    fn hurt_sound(&self) -> Option<&'static str> {
+        Some("crow_hurt")
+    }
+
+    /// A diving crow hits and pulls away, shield or not.
+    fn touch(&mut self) -> Option<i32> {
+        if self.swoop != Swoop::Dive || self.health.is_dead() {
+            return None;
+        }
+        self.retreat();
+        Some(1)
+    }
+
+    fn solid(&self) -> bool {
+        false
+    }
+
+    fn flying(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,206 @@
+use macroquad::prelude::{draw_texture_ex, DrawTextureParams, WHITE};
+
+use crate::animation::*;
+use crate::assets::{Assets, Defs};
+use crate::camera::Camera;
+use crate::collision::{Aabb, Circle};
+use crate::enemies::{Enemy, Senses, Shot};
+use crate::health::Health;
+use crate::map::*;
+use crate::vecs::*;
+
+/// How far a crystal sees.
+const RANGE: f32 = 800.;
+/// Seconds between two arrows.
+const RELOAD: f32 = 1.8;
+/// Pixels per second.
+const ARROW_SPEED: f32 = 840.;
+/// Seconds before a stray arrow is dropped.
+const ARROW_TIME: f32 = 3.;
+
+/// A crystal that never moves, shooting arrows at the player whenever no
+/// wall stands between them.
+pub struct Crystal {
+    pub pos: Vec2,
+    pub size: f32,
+    pub sprite: AnimatedSprite,
+    pub hit: Aabb,
+    pub health: Health,
+    /// Seconds until it can shoot again.
+    pub reload: f32,
+    pub arrows: Vec<Arrow>,
+}
+
+pub struct Arrow {
+    /// The tip.
+    pub pos: Vec2,
+    /// Where the tip was before its last update.
+    pub prev: Vec2,
+    pub velocity: Vec2,
+    pub time: f32,
+    pub die: bool,
+}
+
+impl Crystal {
+    pub fn new(pos: Vec2, defs: &Defs, size: f32) -> Self {
+        let sprite = AnimatedSprite::from_def(defs.animation("crystal"), defs);
+        let idle = &sprite.animations[0];
+        let hit = Aabb::new(
+            pos,
+            Vec2::new(idle.width as f32 * size, idle.height as f32 * size),
+        );
+
+        Self {
+            pos,
+            size,
+            sprite,
+            hit,
+            health: Health::new(3, 0.3),
+            reload: RELOAD,
+            arrows: Vec::new(),
+        }
+    }
+}
+
+impl Enemy for Crystal {
+    fn update(&mut self, dt: f32, senses: &mut Senses) {
+        let (walls, player) = (senses.walls, senses.player);
+        self.health.update(dt);
+        self.sprite.update(dt);
+        self.reload -= dt;
+
+        let center = self.hit.center();
+        let (dx, dy) = (player.x - center.x, player.y - center.y);
+        let seen = dx * dx + dy * dy < RANGE * RANGE && walls.line_clear(center, player);
+        if seen && self.reload <= 0. && !self.health.is_dead() {
+            self.reload = RELOAD;
+            let dir = Vec2::new(dx, dy).normalize();
+            self.arrows.push(Arrow {
+                pos: center,
+                prev: center,
+                velocity: Vec2::new(dir.x * ARROW_SPEED, dir.y * ARROW_SPEED),
+                time: 0.,
+                die: false,
+            });
+            senses.sounds.push("arrow".to_string());
+        }
+
+        for arrow in &mut self.arrows {
+            arrow.update(dt, walls);
+        }
+        self.arrows.retain(|arrow| !arrow.die);
+    }
+
+    fn draw(&self, assets: &Assets, camera: &Camera, walls: &Map) {
+        let size = self.size * self.sprite.animations[self.sprite.cur_animation].height as f32;
+        //draw walls close to the crystal
+        walls.draw_near(&self.pos, size, false, assets, camera);
+
+        //flash while hurt
+        if self.health.invulnerable == 0. || (self.health.invulnerable * 20.) as i32 % 2 == 1 {
+            self.sprite
+                .draw(&self.pos, &self.size, &false, assets, camera);
+        }
+
+        //draw walls close to the crystal
+        walls.draw_near(&self.pos, size, true, assets, camera);
+
+        for arrow in &self.arrows {
+            arrow.draw(assets, camera);
+        }
+    }
+
+    fn hitbox(&self) -> Aabb {
+        self.hit
+    }
+
+    fn health(&self) -> &Health {
+        &self.health
+    }
+
+    /// Shatters on the last hit; it can't be pushed.
+    fn take_damage(&mut self, amount: i32, _from: Vec2) -> bool {
+        if !self.health.damage(amount) {
+            return false;
+        }
+        if self.health.is_dead() {
+            self.sprite.play("shatter");
+            self.sprite.restart();
+        }
+        true
+    }
+
+    /// Done once it has shattered and its arrows have landed.
+    fn is_gone(&self) -> bool {
+        self.health.is_dead() && !self.sprite.playing && self.arrows.is_empty()
+    }
+
+    fn hurt_sound(&self) -> Option<&'static str> {
+        Some("crystal")
+    }
+
+    fn shots(&self) -> Vec<Shot> {
+        self.arrows
+            .iter()
+            .map(|arrow| Shot {
+                collider: arrow.collider(),
+                step: Vec2::new(arrow.pos.x - arrow.prev.x, arrow.pos.y - arrow.prev.y),
+            })
+            .collect()
+    }
+
+    fn land(&mut self, i: usize) {
+        self.arrows[i].die = true;
+    }
+}
+
+impl Arrow {
+    /// Around the tip, the only part that hurts.
+    pub fn collider(&self) -> Circle {
+        Circle::new(self.pos, 8.)
+    }
+
+    fn update(&mut self, dt: f32, walls: &Map) {
+        self.prev = self.pos;
+        self.pos.x += self.velocity.x * dt;
+        self.pos.y += self.velocity.y * dt;
+        self.time += dt;
+        let (x, y) = (self.pos.x / walls.size, self.pos.y / walls.size);
+        let solid = x >= 0.
+            && y >= 0.
+            && walls
+                .def_at(x as usize, y as usize)
+                .is_some_and(|def| def.solid);
+        if solid || self.time > ARROW_TIME {
+            self.die = true;
+        }
+    }
+
+    /// `pos` is the tip, so the texture is drawn behind it, turned to face
+    /// where it flies.
+    fn draw(&self, assets: &Assets, camera: &Camera) {
+        let texture = assets.texture("flecha");
+        let (width, height) = (texture.width() * 3., texture.height() * 3.);
+        let params = DrawTextureParams {
+            dest_size: Some(macroquad::prelude::Vec2::new(
+                width * camera.zoom,
+                height * camera.zoom,
+            )),
+            rotation: self.velocity.y.atan2(self.velocity.x),
+            ..Default::default()
+        };
+        //rotation turns around the middle of the texture
+        let dir = self.velocity.normalize();
+        let middle = Vec2::new(
+            self.pos.x - dir.x * width / 2.,
+            self.pos.y - dir.y * width / 2.,
+        );
+        draw_texture_ex(
+            texture,
+            (middle.x - width / 2.) * camera.zoom + camera.pos.x,
+            (middle.y - height / 2.) * camera.zoom + camera.pos.y,
+            WHITE,
+            params,
+        );
+    }
+}
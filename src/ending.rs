@@ -0,0 +1,42 @@
+use macroquad::prelude::{clear_background, draw_texture, Texture2D, BLACK, WHITE};
+
+use crate::game::Game;
+use crate::input::Action;
+use crate::menu::Menu;
+use crate::scene::{Context, Scene, Transition};
+
+/// One of the full-screen ending pictures, left with `Confirm`.
+pub struct Ending {
+    pub texture: Texture2D,
+    /// Whether leaving goes back into the game, keeping what the player
+    /// found so far, instead of to the menu.
+    pub retry: bool,
+}
+
+impl Ending {
+    pub fn new(texture: Texture2D, retry: bool) -> Self {
+        Self { texture, retry }
+    }
+}
+
+impl Scene for Ending {
+    fn update(&mut self, ctx: &mut Context, _dt: f32) -> Transition {
+        if ctx.bindings.pressed(Action::Confirm) {
+            ctx.audio.play("confirm");
+            if self.retry {
+                return Transition::Switch(Box::new(Game::new(ctx)));
+            }
+            return Transition::Switch(Box::new(Menu::new()));
+        }
+        Transition::None
+    }
+
+    fn draw(&self, _ctx: &Context) {
+        clear_background(BLACK);
+        draw_texture(self.texture, 0., 0., WHITE);
+    }
+
+    fn music(&self) -> Option<&str> {
+        Some("menu")
+    }
+}
@@ -1,42 +1,150 @@
+use crate::assets::{Assets, Defs};
+use crate::boar::Boar;
+use crate::buffalo::Buffalo;
 use crate::camera::Camera;
+use crate::collision::{Aabb, Circle};
 use crate::coruja::Coruja;
-use crate::player::Player;
-use crate::textures::Textures;
+use crate::crow::Crow;
+use crate::crystal::Crystal;
+use crate::health::Health;
+use crate::vecs::Vec2;
 use crate::Map;
 
+/// What an enemy can see and affect while it updates.
+pub struct Senses<'a> {
+    pub walls: &'a Map,
+    pub floors: &'a Map,
+    /// The player's center.
+    pub player: Vec2,
+    pub sounds: &'a mut Vec<String>,
+}
+
+/// A projectile as the player's collision sees it.
+pub struct Shot {
+    pub collider: Circle,
+    /// How far it moved in the last update, to sweep fast shots.
+    pub step: Vec2,
+}
+
+/// Anything hostile in the world. `World` only talks to enemies through
+/// this, so a new kind needs an implementation and a line in `SPAWNERS`.
+pub trait Enemy {
+    fn update(&mut self, dt: f32, senses: &mut Senses);
+    fn draw(&self, assets: &Assets, camera: &Camera, walls: &Map);
+    fn hitbox(&self) -> Aabb;
+    fn health(&self) -> &Health;
+
+    /// Takes a hit coming from `from`. Returns false if it didn't land,
+    /// like while still flashing from the last one.
+    fn take_damage(&mut self, amount: i32, from: Vec2) -> bool;
+
+    fn is_dead(&self) -> bool {
+        self.health().is_dead()
+    }
+
+    /// Whether it can be dropped, after dying and any animation that goes
+    /// with it.
+    fn is_gone(&self) -> bool {
+        self.is_dead()
+    }
+
+    /// Played when `take_damage` lands.
+    fn hurt_sound(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// Damage to the player from touching it right now, if any.
+    fn touch(&mut self) -> Option<i32> {
+        None
+    }
+
+    /// Solid enemies push the player out instead of letting it walk
+    /// through them.
+    fn solid(&self) -> bool {
+        true
+    }
+
+    /// Flying enemies are drawn above everything else.
+    fn flying(&self) -> bool {
+        false
+    }
+
+    /// Bosses lock their arena until they die.
+    fn boss(&self) -> bool {
+        false
+    }
+
+    /// Called when the player walks into a boss's arena.
+    fn wake(&mut self) {}
+
+    fn shots(&self) -> Vec<Shot> {
+        Vec::new()
+    }
+
+    /// The shot at `i` in `shots` hit the player.
+    fn land(&mut self, _i: usize) {}
+}
+
+/// Builds an enemy at a spawn's position.
+type Spawner = fn(Vec2, &Defs) -> Box<dyn Enemy>;
+
+/// Every enemy kind, by the spawn kind the map uses for it.
+const SPAWNERS: &[(&str, Spawner)] = &[
+    ("coruja", |pos, defs| {
+        Box::new(Coruja::new(pos, defs, 2., 8.))
+    }),
+    ("crow", |pos, defs| Box::new(Crow::new(pos, defs, 2.))),
+    ("boar", |pos, defs| Box::new(Boar::new(pos, defs, 2.))),
+    ("buffalo", |pos, defs| Box::new(Buffalo::new(pos, defs, 3.))),
+    ("crystal", |pos, defs| Box::new(Crystal::new(pos, defs, 3.))),
+];
+
+/// An enemy and what the player's sword last did to it.
+pub struct Slot {
+    pub enemy: Box<dyn Enemy>,
+    /// The player's last swing that hit it, so each swing hits once.
+    pub last_swing: u32,
+}
+
+#[derive(Default)]
 pub struct Enemies {
-    pub corujas: Vec<Coruja>,
+    pub list: Vec<Slot>,
 }
 
 impl Enemies {
-    pub fn update(&mut self, walls: &Map, floors: &Map, player: &Player, textures: &Textures) {
-        for coruja in &mut self.corujas {
-            coruja.update(walls, floors, textures);
-            let coruja_hurt = ((coruja.pos.x - player.sword_hit[0].x > player.sword_hit[1].x)
-                || (coruja.pos.y - player.sword_hit[0].y > player.sword_hit[1].y))
-                || !player.attacking
-                || coruja.pos.x < 0.
-                || coruja.pos.y < 0.;
-
-            if coruja_hurt {
-                coruja.sprite.cur_animation = 2;
+    /// Adds an enemy of the map's `kind`, returning false if there is no
+    /// such kind.
+    pub fn spawn(&mut self, kind: &str, pos: Vec2, defs: &Defs) -> bool {
+        match SPAWNERS.iter().find(|(name, _)| *name == kind) {
+            Some((_, spawner)) => {
+                self.list.push(Slot {
+                    enemy: spawner(pos, defs),
+                    last_swing: 0,
+                });
+                true
             }
+            None => false,
+        }
+    }
+
+    pub fn update(&mut self, dt: f32, senses: &mut Senses) {
+        for slot in &mut self.list {
+            slot.enemy.update(dt, senses);
         }
 
-        /*self.corujas.retain(|coruja| {
-            ((coruja.pos.x - player.sword_hit[0].x > player.sword_hit[1].x)
-                || (coruja.pos.y - player.sword_hit[0].y > player.sword_hit[1].y))
-                || !player.attacking
-                || coruja.pos.x < 0.
-                || coruja.pos.y < 0.
-        });*/
+        self.list.retain(|slot| !slot.enemy.is_gone());
     }
 
-    pub fn draw(&self, textures: &Textures, camera: &Camera, walls: &Map) {
-        for coruja in &self.corujas {
-            coruja.draw(textures, camera, walls)
+    pub fn draw(&self, assets: &Assets, camera: &Camera, walls: &Map) {
+        for slot in self.list.iter().filter(|slot| !slot.enemy.flying()) {
+            slot.enemy.draw(assets, camera, walls);
         }
     }
 
-    pub fn attack(&mut self) {}
+    /// Flying enemies go over everything, so they are drawn last.
+    pub fn draw_flying(&self, assets: &Assets, camera: &Camera, walls: &Map) {
+        for slot in self.list.iter().filter(|slot| slot.enemy.flying()) {
+            slot.enemy.draw(assets, camera, walls);
+        }
+    }
 }
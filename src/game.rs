@@ -0,0 +1,219 @@
+use macroquad::prelude::*;
+
+use crate::camera::Camera;
+use crate::ending::Ending;
+use crate::hud::{draw_boss_bar, draw_hearts, draw_message, draw_prompt, draw_skill};
+use crate::input::{Action, Bindings, Input};
+use crate::inventory::Item;
+use crate::map::Map;
+use crate::pause::Pause;
+use crate::pickup::Pickup;
+use crate::scene::{Context, Scene, Transition};
+use crate::vecs::Vec2;
+use crate::world::World;
+use crate::Assets;
+
+/// Seconds a death or a win plays out before its ending is shown.
+const ENDING_DELAY: f32 = 1.5;
+
+/// Gameplay, plus the map editor in debug mode.
+pub struct Game {
+    pub world: World,
+    pub camera: Camera,
+    pub kind: i8,
+    pub wall: bool,
+    pub debug: bool,
+    /// Seconds since the player died, so the death reads before the ending.
+    pub dead: f32,
+    /// Seconds since the boss fell, for the same reason.
+    pub won: f32,
+}
+
+impl Game {
+    pub fn new(ctx: &Context) -> Self {
+        let mut world = World::new(ctx.level.clone(), &ctx.assets.defs)
+            .expect("levels are checked when loaded and when saved");
+        world.restore(&ctx.progress);
+        Self {
+            world,
+            camera: Camera {
+                pos: Vec2::new(90., 90.),
+                zoom: 1.,
+                speed: Vec2::new(0., 0.),
+                speed_limit: Vec2::new(50., 20.),
+            },
+            kind: 1,
+            wall: false,
+            debug: false,
+            dead: 0.,
+            won: 0.,
+        }
+    }
+}
+
+impl Scene for Game {
+    fn update(&mut self, ctx: &mut Context, dt: f32) -> Transition {
+        //new items pause the game until their popup is closed
+        if !self.world.found.is_empty() {
+            let item = self.world.found.remove(0);
+            if let Some(texture) = item.popup() {
+                return Transition::Push(Box::new(Pickup::new(ctx.assets.texture(texture))));
+            }
+        }
+        let mut input = Input::read(&ctx.bindings);
+        //the editor has the mouse, so painting doesn't swing the sword
+        if self.debug {
+            input.attack = false;
+            input.skill = false;
+        }
+        self.world.update(dt, &input);
+        for sound in self.world.sounds.drain(..) {
+            ctx.audio.play(&sound);
+        }
+        ctx.progress = self.world.progress();
+        self.camera.update(dt, &self.world.player);
+        self.camera.pan(&input);
+        if self.debug {
+            edit_map(
+                &mut self.kind,
+                &mut self.world,
+                &mut self.camera,
+                &mut self.wall,
+                &ctx.bindings,
+            );
+            if ctx.bindings.pressed(Action::EditorSave) {
+                match self.world.save(&ctx.assets.defs) {
+                    //later games start from the saved level
+                    Ok(()) => {
+                        ctx.level = self.world.level.clone();
+                        ctx.audio.play("save");
+                    }
+                    Err(err) => self.world.say(format!("Not saved: {}", err)),
+                }
+            }
+        }
+        if input.debug {
+            self.debug = !self.debug;
+        }
+        //a win stands even if the player dies right after it
+        if self.world.won {
+            self.won += dt;
+            if self.won > ENDING_DELAY {
+                let ending = Ending::new(ctx.assets.texture("good_ending"), false);
+                return Transition::Switch(Box::new(ending));
+            }
+        } else if self.world.player.health.is_dead() {
+            self.dead += dt;
+            if self.dead > ENDING_DELAY {
+                //dying starts over with whatever was opened and found
+                let ending = Ending::new(ctx.assets.texture("bad_ending"), true);
+                return Transition::Switch(Box::new(ending));
+            }
+        }
+        if input.pause {
+            return Transition::Push(Box::new(Pause::new()));
+        }
+        Transition::None
+    }
+
+    fn draw(&self, ctx: &Context) {
+        clear_background(DARKGRAY);
+        self.world.draw(&ctx.assets, &self.camera);
+        draw_hearts(&self.world.player.health, &ctx.assets);
+        if self.world.player.inventory.has(Item::Skill) {
+            draw_skill(&self.world.player.skill, &ctx.assets);
+        }
+        if let Some((pos, text)) = self.world.prompt() {
+            let key = ctx
+                .bindings
+                .get(Action::Interact)
+                .first()
+                .map_or("?".to_string(), |binding| binding.name());
+            let pos = Vec2::new(
+                pos.x * self.camera.zoom + self.camera.pos.x,
+                pos.y * self.camera.zoom + self.camera.pos.y,
+            );
+            draw_prompt(&format!("[{}] {}", key, text), pos);
+        }
+        if let Some(health) = self.world.boss() {
+            draw_boss_bar("Buffalo", health);
+        }
+        if let Some((text, _)) = &self.world.message {
+            draw_message(text);
+        }
+        if self.debug {
+            let map = if self.wall {
+                &self.world.walls
+            } else {
+                &self.world.floors
+            };
+            draw_icon(self.kind, &ctx.assets, map);
+            draw_text(&get_fps().to_string(), 10., 80., 40., WHITE);
+        }
+    }
+
+    fn music(&self) -> Option<&str> {
+        Some(self.world.music())
+    }
+}
+
+pub fn edit_map(
+    kind: &mut i8,
+    world: &mut World,
+    camera: &mut Camera,
+    wall: &mut bool,
+    bindings: &Bindings,
+) {
+    let size = world.walls.size;
+    let x = (((mouse_position().0 - camera.pos.x) / camera.zoom) / size) as usize;
+    let y = (((mouse_position().1 - camera.pos.y) / camera.zoom) / size) as usize;
+    let map = if *wall {
+        &mut world.walls
+    } else {
+        &mut world.floors
+    };
+
+    if x < map.width && y < map.height {
+        if bindings.down(Action::EditorPaint) {
+            map.vec[y][x].kind = *kind as u16;
+        } else if bindings.down(Action::EditorErase) {
+            map.vec[y][x].kind = 0;
+        }
+    }
+
+    let n = map.tiles.len() as i8 - 1;
+
+    if bindings.pressed(Action::EditorSwitchLayer) {
+        *wall = !*wall;
+    }
+
+    let scroll = if mouse_wheel().1 > 0. {
+        1.
+    } else if mouse_wheel().1 < 0. {
+        -1.
+    } else {
+        0.
+    };
+    if bindings.down(Action::EditorZoom) {
+        camera.zoom += scroll / 10.;
+    } else {
+        *kind += scroll as i8;
+    }
+
+    if *kind > n {
+        *kind = 1;
+    } else if *kind < 1 {
+        *kind = n;
+    }
+}
+
+/// The tile the editor paints, shrunk into the top left corner.
+pub fn draw_icon(kind: i8, assets: &Assets, map: &Map) {
+    let camera = Camera {
+        pos: Vec2::new(10., 30.),
+        zoom: 1. / 3.,
+        speed: Vec2::new(0., 0.),
+        speed_limit: Vec2::new(0., 0.),
+    };
+    map.draw_tile(kind as u16, Vec2::new(0., 0.), assets, &camera);
+}
@@ -0,0 +1,160 @@
+//! Gamepad state polled once per frame and queried like macroquad's keyboard
+//! functions. The device backend is `gilrs`, behind the `gamepad` feature;
+//! without it no pad is ever connected and every query reads as released.
+
+use std::cell::RefCell;
+
+use crate::vecs::Vec2;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PadButton {
+    South,
+    East,
+    North,
+    West,
+    LeftBumper,
+    RightBumper,
+    LeftTrigger,
+    RightTrigger,
+    Select,
+    Start,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+impl PadButton {
+    pub const ALL: [PadButton; 14] = [
+        PadButton::South,
+        PadButton::East,
+        PadButton::North,
+        PadButton::West,
+        PadButton::LeftBumper,
+        PadButton::RightBumper,
+        PadButton::LeftTrigger,
+        PadButton::RightTrigger,
+        PadButton::Select,
+        PadButton::Start,
+        PadButton::DPadUp,
+        PadButton::DPadDown,
+        PadButton::DPadLeft,
+        PadButton::DPadRight,
+    ];
+}
+
+struct State {
+    #[cfg(feature = "gamepad")]
+    gilrs: Option<gilrs::Gilrs>,
+    down: Vec<PadButton>,
+    pressed: Vec<PadButton>,
+    stick: Vec2,
+}
+
+thread_local! {
+    static STATE: RefCell<State> = RefCell::new(State {
+        #[cfg(feature = "gamepad")]
+        gilrs: gilrs::Gilrs::new().ok(),
+        down: Vec::new(),
+        pressed: Vec::new(),
+        stick: Vec2::new(0., 0.),
+    });
+}
+
+/// Drains pad events; call once per frame before reading input.
+pub fn update() {
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        state.pressed.clear();
+
+        #[cfg(feature = "gamepad")]
+        poll(&mut state);
+    });
+}
+
+#[cfg(feature = "gamepad")]
+fn poll(state: &mut State) {
+    use gilrs::{Axis, Button, EventType};
+
+    let Some(mut gilrs) = state.gilrs.take() else {
+        return;
+    };
+
+    while let Some(event) = gilrs.next_event() {
+        match event.event {
+            EventType::ButtonPressed(button, _) => {
+                if let Some(button) = from_gilrs(button) {
+                    if !state.down.contains(&button) {
+                        state.down.push(button);
+                    }
+                    state.pressed.push(button);
+                }
+            }
+            EventType::ButtonReleased(button, _) => {
+                if let Some(button) = from_gilrs(button) {
+                    state.down.retain(|b| *b != button);
+                }
+            }
+            EventType::Disconnected => state.down.clear(),
+            _ => (),
+        }
+    }
+
+    //gilrs reports up as positive, the screen grows downwards
+    state.stick = match gilrs.gamepads().next() {
+        Some((_, pad)) => Vec2::new(pad.value(Axis::LeftStickX), -pad.value(Axis::LeftStickY)),
+        None => Vec2::new(0., 0.),
+    };
+
+    fn from_gilrs(button: Button) -> Option<PadButton> {
+        Some(match button {
+            Button::South => PadButton::South,
+            Button::East => PadButton::East,
+            Button::North => PadButton::North,
+            Button::West => PadButton::West,
+            Button::LeftTrigger => PadButton::LeftBumper,
+            Button::RightTrigger => PadButton::RightBumper,
+            Button::LeftTrigger2 => PadButton::LeftTrigger,
+            Button::RightTrigger2 => PadButton::RightTrigger,
+            Button::Select => PadButton::Select,
+            Button::Start => PadButton::Start,
+            Button::DPadUp => PadButton::DPadUp,
+            Button::DPadDown => PadButton::DPadDown,
+            Button::DPadLeft => PadButton::DPadLeft,
+            Button::DPadRight => PadButton::DPadRight,
+            _ => return None,
+        })
+    }
+
+    state.gilrs = Some(gilrs);
+}
+
+pub fn is_pad_down(button: PadButton) -> bool {
+    STATE.with(|state| state.borrow().down.contains(&button))
+}
+
+pub fn is_pad_pressed(button: PadButton) -> bool {
+    STATE.with(|state| state.borrow().pressed.contains(&button))
+}
+
+/// Raw left stick position, each axis in -1..1.
+pub fn left_stick() -> Vec2 {
+    STATE.with(|state| state.borrow().stick)
+}
+
+/// Applies a radial deadzone and rescales what's left so the stick still
+/// reaches full speed at the edge. Deadzones outside `0..1` are clamped.
+pub fn apply_deadzone(stick: Vec2, deadzone: f32) -> Vec2 {
+    //a deadzone of 1 would leave the stick dead, and 0/0 a NaN direction
+    let deadzone = if deadzone.is_nan() {
+        0.
+    } else {
+        deadzone.clamp(0., 0.95)
+    };
+    let len = stick.length();
+    if len == 0. || len <= deadzone {
+        return Vec2::new(0., 0.);
+    }
+    let scale = ((len - deadzone) / (1. - deadzone)).min(1.) / len;
+    Vec2::new(stick.x * scale, stick.y * scale)
+}
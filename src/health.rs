@@ -0,0 +1,38 @@
+/// Hit points plus the grace period after a hit during which further damage
+/// is ignored.
+pub struct Health {
+    pub hp: i32,
+    pub max: i32,
+    pub invulnerable: f32,
+    /// Seconds of invulnerability each hit grants.
+    pub grace: f32,
+}
+
+impl Health {
+    pub fn new(max: i32, grace: f32) -> Self {
+        Self {
+            hp: max,
+            max,
+            invulnerable: 0.,
+            grace,
+        }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.invulnerable = (self.invulnerable - dt).max(0.);
+    }
+
+    /// Returns whether the hit landed.
+    pub fn damage(&mut self, amount: i32) -> bool {
+        if self.invulnerable > 0. || self.is_dead() {
+            return false;
+        }
+        self.hp = (self.hp - amount).max(0);
+        self.invulnerable = self.grace;
+        true
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.hp <= 0
+    }
+}
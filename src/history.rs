@@ -1,7 +1,8 @@
-use crate::Textures;
-use macroquad::prelude::{
-    draw_texture, get_frame_time, is_mouse_button_pressed, Color, MouseButton, Texture2D, WHITE,
-};
+use crate::assets::Assets;
+use crate::input::Action;
+use crate::menu::Menu;
+use crate::scene::{Context, Scene, Transition};
+use macroquad::prelude::{draw_texture, Color, Texture2D, WHITE};
 
 pub struct History {
     pub quadrinhos: Vec<Texture2D>,
@@ -14,12 +15,12 @@ pub struct History {
 }
 
 impl History {
-    pub fn new(textures: &Textures) -> Self {
+    pub fn new(assets: &Assets) -> Self {
         Self {
             quadrinhos: vec![
-                textures.quadrinho_1,
-                textures.quadrinho_2,
-                textures.quadrinho_3,
+                assets.texture("quadrinho_1"),
+                assets.texture("quadrinho_2"),
+                assets.texture("quadrinho_3"),
             ],
             cur: 0,
             time: 0.,
@@ -29,27 +30,39 @@ impl History {
             change_color: 0.2,
         }
     }
+}
+
+impl Scene for History {
+    fn update(&mut self, ctx: &mut Context, dt: f32) -> Transition {
+        if self.cur >= self.quadrinhos.len() {
+            return Transition::Switch(Box::new(Menu::new()));
+        }
+
+        self.time += dt;
+        if self.time >= self.on_time {
+            self.color.r -= self.color_sub;
+            self.color.g -= self.color_sub;
+            self.color.b -= self.color_sub;
+            if self.color.r <= self.change_color || ctx.bindings.pressed(Action::Confirm) {
+                self.color.r = 1.;
+                self.color.g = 1.;
+                self.color.b = 1.;
+                self.time = 0.;
+                self.cur += 1;
+            }
+        } else if ctx.bindings.pressed(Action::Confirm) {
+            self.time = self.on_time;
+        }
+        Transition::None
+    }
 
-    pub fn tick(&mut self, scene: &mut i32) {
+    fn draw(&self, _ctx: &Context) {
         if self.cur < self.quadrinhos.len() {
             draw_texture(self.quadrinhos[self.cur], 0., 0., self.color);
-            self.time += get_frame_time();
-            if self.time >= self.on_time {
-                self.color.r -= self.color_sub;
-                self.color.g -= self.color_sub;
-                self.color.b -= self.color_sub;
-                if self.color.r <= self.change_color || is_mouse_button_pressed(MouseButton::Left) {
-                    self.color.r = 1.;
-                    self.color.g = 1.;
-                    self.color.b = 1.;
-                    self.time = 0.;
-                    self.cur += 1;
-                }
-            } else if is_mouse_button_pressed(MouseButton::Left) {
-                self.time = self.on_time;
-            }
-        } else {
-            *scene += 1;
         }
     }
+
+    fn music(&self) -> Option<&str> {
+        Some("menu")
+    }
 }
@@ -0,0 +1,116 @@
+use macroquad::prelude::{
+    draw_rectangle, draw_text, draw_texture_ex, measure_text, screen_height, screen_width, Color,
+    DrawTextureParams, Rect, WHITE,
+};
+
+use crate::assets::Assets;
+use crate::health::Health;
+use crate::skill::{self, Skill};
+use crate::vecs::Vec2;
+
+/// Hit points each heart holds; the heart sheet has a frame per lost point.
+pub const HP_PER_HEART: i32 = 3;
+
+/// Draws a row of hearts in the top right corner, clear of the editor icon.
+pub fn draw_hearts(health: &Health, assets: &Assets) {
+    let heart = assets.defs.sheet("heart");
+    let hearts = (health.max + HP_PER_HEART - 1) / HP_PER_HEART;
+    for i in 0..hearts {
+        let left = (health.hp - i * HP_PER_HEART).clamp(0, HP_PER_HEART);
+        let frame = (HP_PER_HEART - left) as f32;
+        let params = DrawTextureParams {
+            dest_size: Some(macroquad::prelude::Vec2::new(
+                heart.frame_width * 1.5,
+                heart.frame_height * 1.5,
+            )),
+            source: Some(Rect::new(
+                frame * heart.frame_width,
+                0.,
+                heart.frame_width,
+                heart.frame_height,
+            )),
+            ..Default::default()
+        };
+        draw_texture_ex(
+            assets.texture("heart"),
+            screen_width() - (hearts - i) as f32 * heart.frame_width * 1.6 - 10.,
+            10.,
+            WHITE,
+            params,
+        );
+    }
+}
+
+/// A short hint like "[E] Open", with its bottom left corner at `pos` on
+/// screen.
+pub fn draw_prompt(text: &str, pos: Vec2) {
+    let size = measure_text(text, None, 24, 1.);
+    let back = Color::new(0., 0., 0., 0.6);
+    draw_rectangle(
+        pos.x - 4.,
+        pos.y - size.height - 4.,
+        size.width + 8.,
+        size.height + 8.,
+        back,
+    );
+    draw_text(text, pos.x, pos.y, 24., WHITE);
+}
+
+/// A line of text in a box along the bottom of the screen.
+pub fn draw_message(text: &str) {
+    let size = measure_text(text, None, 28, 1.);
+    let back = Color::new(0., 0., 0., 0.75);
+    let y = screen_height() - 80.;
+    draw_rectangle(0., y, screen_width(), 60., back);
+    draw_text(
+        text,
+        (screen_width() - size.width) / 2.,
+        y + 38.,
+        28.,
+        WHITE,
+    );
+}
+
+/// The skill's icon under the hearts, darkened from the top while it cools
+/// down, with a bar of mana below it.
+pub fn draw_skill(skill: &Skill, assets: &Assets) {
+    let icon = assets.defs.sheet("player_skill");
+    let size = icon.frame_width * 2.5;
+    let x = screen_width() - size - 10.;
+    let y = 10. + assets.defs.sheet("heart").frame_height * 1.5 + 10.;
+    let params = DrawTextureParams {
+        dest_size: Some(macroquad::prelude::Vec2::new(size, size)),
+        source: Some(Rect::new(0., 0., icon.frame_width, icon.frame_height)),
+        ..Default::default()
+    };
+    draw_texture_ex(assets.texture("player_skill"), x, y, WHITE, params);
+    let cooling = skill.cooldown / skill::COOLDOWN;
+    draw_rectangle(x, y, size, size * cooling, Color::new(0., 0., 0., 0.6));
+
+    let mana = skill.mana / skill::MAX_MANA;
+    draw_rectangle(x, y + size + 4., size, 6., Color::new(0., 0., 0., 0.6));
+    draw_rectangle(
+        x,
+        y + size + 4.,
+        size * mana,
+        6.,
+        Color::new(0.3, 0.6, 1., 1.),
+    );
+}
+
+/// A long bar across the top of the screen for a boss, with its name.
+pub fn draw_boss_bar(name: &str, health: &Health) {
+    let width = screen_width() * 0.6;
+    let x = (screen_width() - width) / 2.;
+    let y = 50.;
+    let left = health.hp.max(0) as f32 / health.max as f32;
+    draw_rectangle(
+        x - 3.,
+        y - 3.,
+        width + 6.,
+        22.,
+        Color::new(0., 0., 0., 0.75),
+    );
+    draw_rectangle(x, y, width * left, 16., Color::new(0.8, 0.1, 0.1, 1.));
+    draw_text(name, x, y - 10., 28., WHITE);
+}
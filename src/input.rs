@@ -0,0 +1,555 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::gamepad::*;
+use crate::vecs::Vec2;
+use macroquad::prelude::{
+    get_last_key_pressed, is_key_down, is_key_pressed, is_mouse_button_down,
+    is_mouse_button_pressed, KeyCode, MouseButton,
+};
+
+pub const BINDINGS_PATH: &str = "assets/config/bindings.txt";
+
+/// Snapshot of everything the simulation reads from the player in one frame,
+/// so `World::update` can run without a window.
+#[derive(Clone, Copy, Default)]
+pub struct Input {
+    pub x: f32,
+    pub y: f32,
+    pub pan: bool,
+    pub attack: bool,
+    pub interact: bool,
+    pub skill: bool,
+    pub pause: bool,
+    pub debug: bool,
+}
+
+impl Input {
+    pub fn read(bindings: &Bindings) -> Self {
+        let stick = apply_deadzone(left_stick(), bindings.deadzone);
+        let dir = if stick.length() > 0. {
+            stick
+        } else {
+            Vec2::new(
+                bindings.down(Action::MoveRight) as i8 as f32
+                    - bindings.down(Action::MoveLeft) as i8 as f32,
+                bindings.down(Action::MoveDown) as i8 as f32
+                    - bindings.down(Action::MoveUp) as i8 as f32,
+            )
+            .normalize()
+        };
+
+        Self {
+            x: dir.x,
+            y: dir.y,
+            pan: bindings.down(Action::PanCamera),
+            attack: bindings.pressed(Action::Attack),
+            interact: bindings.pressed(Action::Interact),
+            skill: bindings.pressed(Action::UseSkill),
+            pause: bindings.pressed(Action::Pause),
+            debug: bindings.pressed(Action::ToggleDebug),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Action {
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    Attack,
+    UseSkill,
+    Interact,
+    PanCamera,
+    Confirm,
+    Back,
+    Pause,
+    ToggleDebug,
+    EditorPaint,
+    EditorErase,
+    EditorZoom,
+    EditorSwitchLayer,
+    EditorSave,
+}
+
+impl Action {
+    pub const ALL: [Action; 17] = [
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::Attack,
+        Action::UseSkill,
+        Action::Interact,
+        Action::PanCamera,
+        Action::Confirm,
+        Action::Back,
+        Action::Pause,
+        Action::ToggleDebug,
+        Action::EditorPaint,
+        Action::EditorErase,
+        Action::EditorZoom,
+        Action::EditorSwitchLayer,
+        Action::EditorSave,
+    ];
+
+    pub fn name(&self) -> String {
+        format!("{:?}", self)
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|action| action.name() == name)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    Pad(PadButton),
+}
+
+impl Binding {
+    pub fn down(&self) -> bool {
+        match self {
+            Binding::Key(key) => is_key_down(*key),
+            Binding::Mouse(button) => is_mouse_button_down(*button),
+            Binding::Pad(button) => is_pad_down(*button),
+        }
+    }
+
+    pub fn pressed(&self) -> bool {
+        match self {
+            Binding::Key(key) => is_key_pressed(*key),
+            Binding::Mouse(button) => is_mouse_button_pressed(*button),
+            Binding::Pad(button) => is_pad_pressed(*button),
+        }
+    }
+
+    /// Whatever key or mouse button went down this frame, used to capture a
+    /// new binding while rebinding.
+    pub fn last_pressed() -> Option<Self> {
+        if let Some(key) = get_last_key_pressed() {
+            return Some(Binding::Key(key));
+        }
+        if let Some(button) = PadButton::ALL.into_iter().find(|b| is_pad_pressed(*b)) {
+            return Some(Binding::Pad(button));
+        }
+        [MouseButton::Left, MouseButton::Right, MouseButton::Middle]
+            .into_iter()
+            .find(|button| is_mouse_button_pressed(*button))
+            .map(Binding::Mouse)
+    }
+
+    pub fn is_pad(&self) -> bool {
+        matches!(self, Binding::Pad(_))
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            Binding::Key(key) => format!("{:?}", key),
+            Binding::Mouse(button) => format!("Mouse{:?}", button),
+            Binding::Pad(button) => format!("Pad{:?}", button),
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        if let Some(button) = name.strip_prefix("Mouse") {
+            return [MouseButton::Left, MouseButton::Right, MouseButton::Middle]
+                .into_iter()
+                .find(|b| format!("{:?}", b) == button)
+                .map(Binding::Mouse);
+        }
+        if let Some(button) = name.strip_prefix("Pad") {
+            return PadButton::ALL
+                .into_iter()
+                .find(|b| format!("{:?}", b) == button)
+                .map(Binding::Pad);
+        }
+        KEYS.iter()
+            .copied()
+            .find(|key| format!("{:?}", key) == name)
+            .map(Binding::Key)
+    }
+}
+
+/// Maps every `Action` to the keys and buttons that trigger it. Systems ask
+/// for actions, never for raw keys, so players can rebind everything.
+pub struct Bindings {
+    pub map: HashMap<Action, Vec<Binding>>,
+    pub deadzone: f32,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        use Binding::*;
+
+        let map = HashMap::from([
+            (
+                Action::MoveUp,
+                vec![Key(KeyCode::W), Key(KeyCode::Up), Pad(PadButton::DPadUp)],
+            ),
+            (
+                Action::MoveDown,
+                vec![
+                    Key(KeyCode::S),
+                    Key(KeyCode::Down),
+                    Pad(PadButton::DPadDown),
+                ],
+            ),
+            (
+                Action::MoveLeft,
+                vec![
+                    Key(KeyCode::A),
+                    Key(KeyCode::Left),
+                    Pad(PadButton::DPadLeft),
+                ],
+            ),
+            (
+                Action::MoveRight,
+                vec![
+                    Key(KeyCode::D),
+                    Key(KeyCode::Right),
+                    Pad(PadButton::DPadRight),
+                ],
+            ),
+            (
+                Action::Attack,
+                vec![Mouse(MouseButton::Left), Pad(PadButton::West)],
+            ),
+            (
+                Action::UseSkill,
+                vec![Mouse(MouseButton::Right), Pad(PadButton::North)],
+            ),
+            (
+                Action::Interact,
+                vec![Key(KeyCode::E), Pad(PadButton::RightBumper)],
+            ),
+            (
+                Action::PanCamera,
+                vec![Key(KeyCode::LeftShift), Pad(PadButton::LeftTrigger)],
+            ),
+            (
+                Action::Confirm,
+                vec![
+                    Key(KeyCode::Enter),
+                    Key(KeyCode::Space),
+                    Pad(PadButton::South),
+                ],
+            ),
+            (
+                Action::Back,
+                vec![Key(KeyCode::Backspace), Pad(PadButton::East)],
+            ),
+            (
+                Action::Pause,
+                vec![Key(KeyCode::Escape), Pad(PadButton::Start)],
+            ),
+            (Action::ToggleDebug, vec![Key(KeyCode::L)]),
+            (Action::EditorPaint, vec![Mouse(MouseButton::Left)]),
+            (Action::EditorErase, vec![Mouse(MouseButton::Right)]),
+            (Action::EditorZoom, vec![Key(KeyCode::LeftControl)]),
+            (Action::EditorSwitchLayer, vec![Key(KeyCode::F)]),
+            (Action::EditorSave, vec![Key(KeyCode::P)]),
+        ]);
+
+        Self { map, deadzone: 0.2 }
+    }
+}
+
+impl Bindings {
+    /// Loads bindings from `path`, keeping the default for every action the
+    /// file doesn't mention. A missing file falls back to defaults, and
+    /// broken lines are reported and skipped.
+    pub async fn from_file(path: &str) -> Self {
+        let string = match macroquad::file::load_string(path).await {
+            Ok(string) => string,
+            Err(_) => return Self::default(),
+        };
+
+        let (bindings, errors) = Self::parse(&string);
+        for err in errors {
+            println!("{}: {}, skipped", path, err);
+        }
+        bindings
+    }
+
+    /// Parses lines like `MoveUp = W, Up` or `Deadzone = 0.2`; `#` starts a
+    /// comment. Also returns why each line that didn't parse was left out.
+    pub fn parse(string: &str) -> (Self, Vec<String>) {
+        let mut bindings = Self::default();
+        let mut errors = Vec::new();
+
+        for (n, line) in string.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Err(err) = bindings.parse_line(line) {
+                errors.push(format!("line {}: {}", n + 1, err));
+            }
+        }
+
+        (bindings, errors)
+    }
+
+    fn parse_line(&mut self, line: &str) -> Result<(), String> {
+        let (action, keys) = line
+            .split_once('=')
+            .ok_or("expected `Action = Key, ...`".to_string())?;
+        if action.trim() == "Deadzone" {
+            let invalid = || format!("invalid deadzone `{}`, expected 0 to below 1", keys.trim());
+            let deadzone: f32 = keys.trim().parse().map_err(|_| invalid())?;
+            if !(0. ..1.).contains(&deadzone) {
+                return Err(invalid());
+            }
+            self.deadzone = deadzone;
+            return Ok(());
+        }
+        let action = Action::from_name(action.trim())
+            .ok_or(format!("unknown action `{}`", action.trim()))?;
+
+        let mut list = Vec::new();
+        for key in keys.split(',').map(str::trim).filter(|k| !k.is_empty()) {
+            list.push(Binding::from_name(key).ok_or(format!("unknown key `{}`", key))?);
+        }
+        self.map.insert(action, list);
+        Ok(())
+    }
+
+    pub fn to_file(&self, path: &str) -> io::Result<()> {
+        let mut string = format!("Deadzone = {}\n", self.deadzone);
+        for action in Action::ALL {
+            let keys: Vec<String> = self.get(action).iter().map(Binding::name).collect();
+            string += &format!("{} = {}\n", action.name(), keys.join(", "));
+        }
+
+        if let Some(dir) = Path::new(path).parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, string)
+    }
+
+    pub fn get(&self, action: Action) -> &[Binding] {
+        self.map.get(&action).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    pub fn down(&self, action: Action) -> bool {
+        self.get(action).iter().any(Binding::down)
+    }
+
+    pub fn pressed(&self, action: Action) -> bool {
+        self.get(action).iter().any(Binding::pressed)
+    }
+
+    /// Replaces the bindings of `action` on the same device as `binding`,
+    /// so rebinding from the keyboard keeps the gamepad buttons and the other
+    /// way around. Keyboard and mouse count as one device.
+    pub fn rebind(&mut self, action: Action, binding: Binding) {
+        let list = self.map.entry(action).or_default();
+        list.retain(|old| old.is_pad() != binding.is_pad());
+        list.push(binding);
+    }
+
+    /// Binds `action` to the next key or button pressed. Call it once per
+    /// frame while waiting; returns `true` once a binding was captured.
+    pub fn capture(&mut self, action: Action) -> bool {
+        match Binding::last_pressed() {
+            Some(binding) => {
+                self.rebind(action, binding);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+const KEYS: [KeyCode; 120] = [
+    KeyCode::Space,
+    KeyCode::Apostrophe,
+    KeyCode::Comma,
+    KeyCode::Minus,
+    KeyCode::Period,
+    KeyCode::Slash,
+    KeyCode::Key0,
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+    KeyCode::Semicolon,
+    KeyCode::Equal,
+    KeyCode::A,
+    KeyCode::B,
+    KeyCode::C,
+    KeyCode::D,
+    KeyCode::E,
+    KeyCode::F,
+    KeyCode::G,
+    KeyCode::H,
+    KeyCode::I,
+    KeyCode::J,
+    KeyCode::K,
+    KeyCode::L,
+    KeyCode::M,
+    KeyCode::N,
+    KeyCode::O,
+    KeyCode::P,
+    KeyCode::Q,
+    KeyCode::R,
+    KeyCode::S,
+    KeyCode::T,
+    KeyCode::U,
+    KeyCode::V,
+    KeyCode::W,
+    KeyCode::X,
+    KeyCode::Y,
+    KeyCode::Z,
+    KeyCode::LeftBracket,
+    KeyCode::Backslash,
+    KeyCode::RightBracket,
+    KeyCode::GraveAccent,
+    KeyCode::World1,
+    KeyCode::World2,
+    KeyCode::Escape,
+    KeyCode::Enter,
+    KeyCode::Tab,
+    KeyCode::Backspace,
+    KeyCode::Insert,
+    KeyCode::Delete,
+    KeyCode::Right,
+    KeyCode::Left,
+    KeyCode::Down,
+    KeyCode::Up,
+    KeyCode::PageUp,
+    KeyCode::PageDown,
+    KeyCode::Home,
+    KeyCode::End,
+    KeyCode::CapsLock,
+    KeyCode::ScrollLock,
+    KeyCode::NumLock,
+    KeyCode::PrintScreen,
+    KeyCode::Pause,
+    KeyCode::F1,
+    KeyCode::F2,
+    KeyCode::F3,
+    KeyCode::F4,
+    KeyCode::F5,
+    KeyCode::F6,
+    KeyCode::F7,
+    KeyCode::F8,
+    KeyCode::F9,
+    KeyCode::F10,
+    KeyCode::F11,
+    KeyCode::F12,
+    KeyCode::F13,
+    KeyCode::F14,
+    KeyCode::F15,
+    KeyCode::F16,
+    KeyCode::F17,
+    KeyCode::F18,
+    KeyCode::F19,
+    KeyCode::F20,
+    KeyCode::F21,
+    KeyCode::F22,
+    KeyCode::F23,
+    KeyCode::F24,
+    KeyCode::F25,
+    KeyCode::Kp0,
+    KeyCode::Kp1,
+    KeyCode::Kp2,
+    KeyCode::Kp3,
+    KeyCode::Kp4,
+    KeyCode::Kp5,
+    KeyCode::Kp6,
+    KeyCode::Kp7,
+    KeyCode::Kp8,
+    KeyCode::Kp9,
+    KeyCode::KpDecimal,
+    KeyCode::KpDivide,
+    KeyCode::KpMultiply,
+    KeyCode::KpSubtract,
+    KeyCode::KpAdd,
+    KeyCode::KpEnter,
+    KeyCode::KpEqual,
+    KeyCode::LeftShift,
+    KeyCode::LeftControl,
+    KeyCode::LeftAlt,
+    KeyCode::LeftSuper,
+    KeyCode::RightShift,
+    KeyCode::RightControl,
+    KeyCode::RightAlt,
+    KeyCode::RightSuper,
+    KeyCode::Menu,
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_bindings_and_the_deadzone() {
+        let (bindings, errors) = Bindings::parse("Deadzone = 0.3\nAttack = J, PadWest # swing\n");
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(bindings.deadzone, 0.3);
+        assert_eq!(
+            bindings.get(Action::Attack),
+            [Binding::Key(KeyCode::J), Binding::Pad(PadButton::West)]
+        );
+    }
+
+    #[test]
+    fn a_broken_line_keeps_the_rest() {
+        let (bindings, errors) = Bindings::parse("Attack = Jay\nInteract = Q\n");
+        assert_eq!(errors, ["line 1: unknown key `Jay`"]);
+        assert_eq!(
+            bindings.get(Action::Attack),
+            Bindings::default().get(Action::Attack)
+        );
+        assert_eq!(bindings.get(Action::Interact), [Binding::Key(KeyCode::Q)]);
+    }
+
+    #[test]
+    fn deadzones_outside_zero_to_one_are_rejected() {
+        for deadzone in ["1", "-0.1", "NaN"] {
+            let (bindings, errors) = Bindings::parse(&format!("Deadzone = {}", deadzone));
+            assert_eq!(errors.len(), 1, "{}", deadzone);
+            assert_eq!(bindings.deadzone, Bindings::default().deadzone);
+        }
+    }
+
+    #[test]
+    fn a_centered_stick_stays_still_whatever_the_deadzone() {
+        for deadzone in [-0.5, 0., 0.2, 1., f32::NAN] {
+            let dir = apply_deadzone(Vec2::new(0., 0.), deadzone);
+            assert_eq!((dir.x, dir.y), (0., 0.), "{}", deadzone);
+            let dir = apply_deadzone(Vec2::new(1., 0.), deadzone);
+            assert_eq!((dir.x, dir.y), (1., 0.), "{}", deadzone);
+        }
+    }
+
+    #[test]
+    fn rebinding_keeps_the_other_device() {
+        let mut bindings = Bindings::default();
+        bindings.rebind(Action::MoveUp, Binding::Key(KeyCode::I));
+        assert_eq!(
+            bindings.get(Action::MoveUp),
+            [Binding::Pad(PadButton::DPadUp), Binding::Key(KeyCode::I)]
+        );
+        bindings.rebind(Action::MoveUp, Binding::Pad(PadButton::North));
+        assert_eq!(
+            bindings.get(Action::MoveUp),
+            [Binding::Key(KeyCode::I), Binding::Pad(PadButton::North)]
+        );
+    }
+}
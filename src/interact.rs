@@ -0,0 +1,226 @@
+use crate::animation::AnimatedSprite;
+use crate::collision::Aabb;
+use crate::inventory::Item;
+use crate::level::Object;
+use crate::map::{Interaction, Map};
+use crate::vecs::Vec2;
+
+/// Seconds a door takes to swing open.
+const DOOR_TIME: f32 = 0.3;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum State {
+    Closed,
+    Opening,
+    Open,
+    /// Closed until the player brings a key.
+    Locked,
+    /// Shut for a boss fight, with no way to open it.
+    Sealed,
+}
+
+/// A wall tile the player can use: a door, a chest or a statue.
+pub struct Interactable {
+    pub x: usize,
+    pub y: usize,
+    pub kind: u16,
+    pub interaction: Interaction,
+    pub state: State,
+    /// This tile's own copy of its animation, so opening one chest leaves the
+    /// others shut.
+    pub sprite: Option<AnimatedSprite>,
+    pub loot: Vec<Item>,
+    pub text: Option<String>,
+    /// Part of the boss arena's walls.
+    pub arena: bool,
+    time: f32,
+}
+
+impl Interactable {
+    /// One interactable per wall tile that has an interaction, set up from
+    /// the level's objects. Statues only have something to say when their
+    /// object gives them a text.
+    pub fn from_map(walls: &Map, objects: &[Object]) -> Result<Vec<Self>, String> {
+        let mut list = Vec::new();
+        for (y, row) in walls.vec.iter().enumerate() {
+            for (x, tile) in row.iter().enumerate() {
+                let def = match walls.def(tile.kind) {
+                    Some(def) if def.interaction != Interaction::None => def,
+                    _ => continue,
+                };
+                let object = objects.iter().find(|object| object.x == x && object.y == y);
+                let text = object.and_then(|object| object.text.clone());
+                if def.interaction == Interaction::Statue && text.is_none() {
+                    continue;
+                }
+                let mut loot = Vec::new();
+                for name in object.iter().flat_map(|object| &object.loot) {
+                    loot.push(
+                        Item::from_name(name)
+                            .ok_or(format!("unknown item `{}` at {}, {}", name, x, y))?,
+                    );
+                }
+                let sprite = if def.animated {
+                    walls.sprites.get(&def.sprite).cloned()
+                } else {
+                    None
+                };
+
+                list.push(Self {
+                    x,
+                    y,
+                    kind: tile.kind,
+                    interaction: def.interaction,
+                    state: if object.is_some_and(|object| object.locked) {
+                        State::Locked
+                    } else {
+                        State::Closed
+                    },
+                    sprite,
+                    loot,
+                    text,
+                    arena: object.is_some_and(|object| object.arena),
+                    time: 0.,
+                });
+            }
+        }
+        Ok(list)
+    }
+
+    pub fn bounds(&self, size: f32) -> Aabb {
+        Aabb::new(
+            Vec2::new(self.x as f32 * size, self.y as f32 * size),
+            Vec2::new(size, size),
+        )
+    }
+
+    /// What interacting would do, shown next to the interact key.
+    pub fn prompt(&self) -> Option<&'static str> {
+        match (self.interaction, self.state) {
+            (Interaction::Statue, _) => Some("Read"),
+            (_, State::Locked) => Some("Unlock"),
+            (_, State::Closed) => Some("Open"),
+            _ => None,
+        }
+    }
+
+    /// Starts opening; a door or chest can't be closed again.
+    pub fn open(&mut self) {
+        self.state = State::Opening;
+        self.time = 0.;
+        if let Some(sprite) = &mut self.sprite {
+            sprite.restart();
+        }
+    }
+
+    /// Jumps straight to the open state, for things opened in an earlier
+    /// attempt.
+    pub fn set_open(&mut self, walls: &mut Map) {
+        self.state = State::Open;
+        if let Some(sprite) = &mut self.sprite {
+            let animation = &mut sprite.animations[sprite.cur_animation];
+            animation.cur_frame = animation.frames - 1;
+            sprite.playing = false;
+        }
+        if self.interaction == Interaction::Door {
+            walls.vec[self.y][self.x].kind = 0;
+        }
+    }
+
+    /// Returns true on the update it finishes opening.
+    pub fn update(&mut self, dt: f32, walls: &mut Map) -> bool {
+        if self.state != State::Opening {
+            return false;
+        }
+        self.time += dt;
+        let opened = match &mut self.sprite {
+            Some(sprite) => {
+                sprite.update(dt);
+                //chests give their loot when the lid is up, not when the
+                //animation ends
+                sprite.fired("open") || !sprite.playing
+            }
+            None => self.time >= DOOR_TIME,
+        };
+        if opened {
+            self.set_open(walls);
+        }
+        opened
+    }
+}
+
+/// The room a boss is fought in, given by the doors flagged `arena` around
+/// it. Those doors shut while the fight is on and go back to how they were
+/// once it is over.
+pub struct Arena {
+    /// Indices into the interactables.
+    pub doors: Vec<usize>,
+    /// The inside of the room, between the doors.
+    pub bounds: Aabb,
+    pub sealed: bool,
+    /// How each door was before being sealed.
+    before: Vec<State>,
+}
+
+impl Arena {
+    /// `None` if the level flags no doors.
+    pub fn new(interactables: &[Interactable], size: f32) -> Option<Self> {
+        let doors: Vec<usize> = (0..interactables.len())
+            .filter(|&i| interactables[i].arena)
+            .collect();
+        if doors.is_empty() {
+            return None;
+        }
+        let (mut x1, mut y1, mut x2, mut y2) = (usize::MAX, usize::MAX, 0, 0);
+        for &i in &doors {
+            let door = &interactables[i];
+            (x1, y1) = (x1.min(door.x), y1.min(door.y));
+            (x2, y2) = (x2.max(door.x), y2.max(door.y));
+        }
+        //the doors sit in the walls, so the room starts a tile in
+        let bounds = Aabb::new(
+            Vec2::new((x1 + 1) as f32 * size, (y1 + 1) as f32 * size),
+            Vec2::new(
+                x2.saturating_sub(x1 + 1) as f32 * size,
+                y2.saturating_sub(y1 + 1) as f32 * size,
+            ),
+        );
+        Some(Self {
+            doors,
+            bounds,
+            sealed: false,
+            before: Vec::new(),
+        })
+    }
+
+    pub fn seal(&mut self, interactables: &mut [Interactable], walls: &mut Map) {
+        self.sealed = true;
+        self.before = Vec::new();
+        for &i in &self.doors {
+            let door = &mut interactables[i];
+            self.before.push(door.state);
+            door.state = State::Sealed;
+            walls.vec[door.y][door.x].kind = door.kind;
+        }
+    }
+
+    /// Whether the interactable at `i` is one of the doors and was open
+    /// before the arena sealed it.
+    pub fn was_open(&self, i: usize) -> bool {
+        self.doors
+            .iter()
+            .zip(&self.before)
+            .any(|(&door, &state)| door == i && matches!(state, State::Open | State::Opening))
+    }
+
+    pub fn release(&mut self, interactables: &mut [Interactable], walls: &mut Map) {
+        self.sealed = false;
+        for (&i, &state) in self.doors.iter().zip(&self.before) {
+            let door = &mut interactables[i];
+            match state {
+                State::Open | State::Opening => door.set_open(walls),
+                state => door.state = state,
+            }
+        }
+    }
+}
@@ -0,0 +1,61 @@
+/// Something the player can carry. Chests name these in their `loot`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Item {
+    Shield,
+    /// A sharper blade that hits twice as hard.
+    Sword,
+    Skill,
+    Key,
+}
+
+impl Item {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "shield" => Some(Item::Shield),
+            "sword" => Some(Item::Sword),
+            "skill" => Some(Item::Skill),
+            "key" => Some(Item::Key),
+            _ => None,
+        }
+    }
+
+    /// The texture shown when this item is picked up, if it gets a popup.
+    pub fn popup(&self) -> Option<&'static str> {
+        match self {
+            Item::Shield => Some("got_shield"),
+            Item::Sword => Some("got_sword"),
+            Item::Skill => Some("got_skill"),
+            Item::Key => None,
+        }
+    }
+}
+
+/// Everything the player has picked up. Keys stack; the rest are only ever
+/// held once.
+#[derive(Clone, Default)]
+pub struct Inventory {
+    pub items: Vec<Item>,
+}
+
+impl Inventory {
+    pub fn has(&self, item: Item) -> bool {
+        self.items.contains(&item)
+    }
+
+    pub fn add(&mut self, item: Item) {
+        if item == Item::Key || !self.has(item) {
+            self.items.push(item);
+        }
+    }
+
+    /// Uses up one `item`, returning false if there was none.
+    pub fn take(&mut self, item: Item) -> bool {
+        match self.items.iter().position(|&held| held == item) {
+            Some(i) => {
+                self.items.remove(i);
+                true
+            }
+            None => false,
+        }
+    }
+}
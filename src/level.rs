@@ -0,0 +1,393 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+
+use serde::{Deserialize, Serialize};
+
+pub const LEVEL_PATH: &str = "assets/world-data/world.json";
+pub const LEVEL_VERSION: u32 = 1;
+
+/// Everything needed to build a `World`: every tile layer, entity spawns and
+/// free-form metadata, in one versioned JSON file.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Level {
+    /// Where the editor saves this level.
+    #[serde(skip)]
+    pub path: String,
+    pub version: u32,
+    pub width: usize,
+    pub height: usize,
+    #[serde(default)]
+    pub metadata: HashMap<String, String>,
+    #[serde(default)]
+    pub spawns: Vec<Spawn>,
+    #[serde(default)]
+    pub objects: Vec<Object>,
+    pub layers: Vec<Layer>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Layer {
+    pub name: String,
+    pub tiles: Vec<Vec<u16>>,
+}
+
+/// An entity placed on the map, in tile coordinates.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Spawn {
+    pub kind: String,
+    pub x: usize,
+    pub y: usize,
+}
+
+/// Settings for the interactable wall tile at `x`, `y`, like what a chest
+/// holds or whether a door is locked.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Object {
+    pub x: usize,
+    pub y: usize,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub locked: bool,
+    /// Shuts while a boss fight is on, closing the boss in with the player.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub arena: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub loot: Vec<String>,
+    /// What a statue says when inspected.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+#[derive(Debug)]
+pub enum LevelError {
+    Io {
+        path: String,
+    },
+    Write {
+        path: String,
+        message: String,
+    },
+    Parse {
+        path: String,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    Invalid {
+        path: String,
+        message: String,
+    },
+}
+
+impl fmt::Display for LevelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LevelError::Io { path } => write!(f, "{}: could not read file", path),
+            LevelError::Write { path, message } => {
+                write!(f, "{}: could not write file: {}", path, message)
+            }
+            LevelError::Parse {
+                path,
+                line,
+                column,
+                message,
+            } => write!(f, "{}:{}:{}: {}", path, line, column, message),
+            LevelError::Invalid { path, message } => write!(f, "{}: {}", path, message),
+        }
+    }
+}
+
+impl Level {
+    pub async fn from_file(path: &str) -> Result<Self, LevelError> {
+        let string = macroquad::file::load_string(path)
+            .await
+            .map_err(|_| LevelError::Io {
+                path: path.to_string(),
+            })?;
+        let mut level = Self::parse(&string, path)?;
+        level.path = path.to_string();
+        Ok(level)
+    }
+
+    pub fn parse(string: &str, path: &str) -> Result<Self, LevelError> {
+        let level: Level = serde_json::from_str(string).map_err(|err| {
+            //serde_json appends its own "at line x column y" to the message
+            let message = err.to_string();
+            let message = match message.rfind(" at line ") {
+                Some(i) => message[..i].to_string(),
+                None => message,
+            };
+            LevelError::Parse {
+                path: path.to_string(),
+                line: err.line(),
+                column: err.column(),
+                message,
+            }
+        })?;
+        level.validate().map_err(|message| LevelError::Invalid {
+            path: path.to_string(),
+            message,
+        })?;
+        Ok(level)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.version > LEVEL_VERSION {
+            return Err(format!(
+                "version {} is newer than the supported version {}",
+                self.version, LEVEL_VERSION
+            ));
+        }
+
+        for layer in &self.layers {
+            if layer.tiles.len() > self.height {
+                return Err(format!(
+                    "layer `{}` has {} rows but the level is {} tall",
+                    layer.name,
+                    layer.tiles.len(),
+                    self.height
+                ));
+            }
+            let width = layer.tiles.first().map_or(0, Vec::len);
+            if width > self.width {
+                return Err(format!(
+                    "layer `{}` is {} tiles wide but the level is {} wide",
+                    layer.name, width, self.width
+                ));
+            }
+            if let Some(y) = layer.tiles.iter().position(|row| row.len() != width) {
+                return Err(format!(
+                    "layer `{}` row {} has {} tiles, expected {}",
+                    layer.name,
+                    y,
+                    layer.tiles[y].len(),
+                    width
+                ));
+            }
+        }
+
+        for spawn in &self.spawns {
+            if spawn.x >= self.width || spawn.y >= self.height {
+                return Err(format!(
+                    "spawn `{}` at {},{} is outside the level",
+                    spawn.kind, spawn.x, spawn.y
+                ));
+            }
+        }
+
+        for object in &self.objects {
+            if object.x >= self.width || object.y >= self.height {
+                return Err(format!(
+                    "object at {},{} is outside the level",
+                    object.x, object.y
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn layer(&self, name: &str) -> Option<&Layer> {
+        self.layers.iter().find(|layer| layer.name == name)
+    }
+
+    pub fn layer_mut(&mut self, name: &str) -> Option<&mut Layer> {
+        self.layers.iter_mut().find(|layer| layer.name == name)
+    }
+
+    /// Builds a level from the old `floors.txt`/`walls.txt` pair, one digit
+    /// per tile.
+    pub fn from_legacy(
+        floors: &str,
+        floors_path: &str,
+        walls: &str,
+        walls_path: &str,
+    ) -> Result<Self, LevelError> {
+        let floors = Layer::from_legacy("floors", floors, floors_path)?;
+        let walls = Layer::from_legacy("walls", walls, walls_path)?;
+
+        let width = floors.width().max(walls.width());
+        let height = floors.tiles.len().max(walls.tiles.len());
+
+        let level = Level {
+            path: String::new(),
+            version: LEVEL_VERSION,
+            width,
+            height,
+            metadata: HashMap::new(),
+            spawns: Vec::new(),
+            objects: Vec::new(),
+            layers: vec![floors, walls],
+        };
+        level.validate().map_err(|message| LevelError::Invalid {
+            path: format!("{}, {}", floors_path, walls_path),
+            message,
+        })?;
+        Ok(level)
+    }
+
+    pub fn to_file(&self, path: &str) -> io::Result<()> {
+        fs::write(path, self.to_json())
+    }
+
+    /// Pretty JSON with one tile row per line, so map diffs stay readable.
+    pub fn to_json(&self) -> String {
+        let mut json = String::from("{\n");
+        json += &format!("  \"version\": {},\n", self.version);
+        json += &format!("  \"width\": {},\n", self.width);
+        json += &format!("  \"height\": {},\n", self.height);
+
+        let mut metadata: Vec<_> = self.metadata.iter().collect();
+        metadata.sort();
+        json += "  \"metadata\": {";
+        for (i, (key, value)) in metadata.iter().enumerate() {
+            json += if i == 0 { "\n" } else { ",\n" };
+            json += &format!(
+                "    {}: {}",
+                serde_json::to_string(key).unwrap(),
+                serde_json::to_string(value).unwrap()
+            );
+        }
+        json += if metadata.is_empty() {
+            "},\n"
+        } else {
+            "\n  },\n"
+        };
+
+        json += "  \"spawns\": [";
+        for (i, spawn) in self.spawns.iter().enumerate() {
+            json += if i == 0 { "\n" } else { ",\n" };
+            json += &format!("    {}", serde_json::to_string(spawn).unwrap());
+        }
+        json += if self.spawns.is_empty() {
+            "],\n"
+        } else {
+            "\n  ],\n"
+        };
+
+        json += "  \"objects\": [";
+        for (i, object) in self.objects.iter().enumerate() {
+            json += if i == 0 { "\n" } else { ",\n" };
+            json += &format!("    {}", serde_json::to_string(object).unwrap());
+        }
+        json += if self.objects.is_empty() {
+            "],\n"
+        } else {
+            "\n  ],\n"
+        };
+
+        json += "  \"layers\": [";
+        for (i, layer) in self.layers.iter().enumerate() {
+            json += if i == 0 { "\n" } else { ",\n" };
+            json += "    {\n";
+            json += &format!(
+                "      \"name\": {},\n",
+                serde_json::to_string(&layer.name).unwrap()
+            );
+            json += "      \"tiles\": [";
+            for (y, row) in layer.tiles.iter().enumerate() {
+                json += if y == 0 { "\n" } else { ",\n" };
+                json += &format!("        {}", serde_json::to_string(row).unwrap());
+            }
+            json += if layer.tiles.is_empty() {
+                "]\n"
+            } else {
+                "\n      ]\n"
+            };
+            json += "    }";
+        }
+        json += if self.layers.is_empty() {
+            "]\n"
+        } else {
+            "\n  ]\n"
+        };
+        json += "}\n";
+
+        json
+    }
+}
+
+impl Layer {
+    pub fn width(&self) -> usize {
+        self.tiles.first().map_or(0, Vec::len)
+    }
+
+    pub fn from_legacy(name: &str, string: &str, path: &str) -> Result<Self, LevelError> {
+        let mut tiles = Vec::new();
+        for (y, line) in string.lines().enumerate() {
+            let mut row = Vec::new();
+            for (x, c) in line.trim_end_matches('\r').chars().enumerate() {
+                let kind = c.to_digit(10).ok_or(LevelError::Parse {
+                    path: path.to_string(),
+                    line: y + 1,
+                    column: x + 1,
+                    message: format!("expected a tile digit, found `{}`", c),
+                })?;
+                row.push(kind as u16);
+            }
+            tiles.push(row);
+        }
+
+        Ok(Layer {
+            name: name.to_string(),
+            tiles,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_a_level() {
+        let string = r#"{
+            "version": 1,
+            "width": 2,
+            "height": 1,
+            "spawns": [{"kind": "player", "x": 1, "y": 0}],
+            "layers": [{"name": "floors", "tiles": [[1, 2]]}]
+        }"#;
+        let level = Level::parse(string, "level.json").unwrap();
+        assert_eq!((level.width, level.height), (2, 1));
+        assert_eq!(level.spawns[0].kind, "player");
+        assert_eq!(level.layer("floors").unwrap().tiles, [[1, 2]]);
+    }
+
+    #[test]
+    fn a_syntax_error_points_at_its_line_and_column() {
+        let string = "{\n  \"version\": 1,\n  \"width\": ?\n}";
+        let err = Level::parse(string, "level.json").err().unwrap();
+        assert_eq!(err.to_string(), "level.json:3:12: expected value");
+    }
+
+    #[test]
+    fn converts_legacy_maps() {
+        let level =
+            Level::from_legacy("01\r\n23\n", "floors.txt", "10\n00\n", "walls.txt").unwrap();
+        assert_eq!((level.width, level.height), (2, 2));
+        assert_eq!(level.layer("floors").unwrap().tiles, [[0, 1], [2, 3]]);
+        assert_eq!(level.layer("walls").unwrap().tiles, [[1, 0], [0, 0]]);
+    }
+
+    #[test]
+    fn a_legacy_map_needs_digits_in_even_rows() {
+        let err = Level::from_legacy("01\n2x\n", "floors.txt", "", "walls.txt")
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.to_string(),
+            "floors.txt:2:2: expected a tile digit, found `x`"
+        );
+
+        let err = Level::from_legacy("01\n2\n", "floors.txt", "", "walls.txt")
+            .err()
+            .unwrap();
+        assert!(matches!(err, LevelError::Invalid { .. }), "{}", err);
+    }
+}
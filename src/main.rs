@@ -1,257 +1,155 @@
 use macroquad::prelude::*;
-mod textures;
-use textures::*;
+mod assets;
+mod audio;
+mod boar;
+mod buffalo;
+use assets::*;
+use audio::*;
+mod map;
 mod player;
-use player::*;
 mod vecs;
-use vecs::Vec2;
-mod map;
 use map::*;
 mod camera;
+mod collision;
 use camera::Camera;
 mod animation;
 mod coruja;
-use coruja::Coruja;
+mod crow;
+mod crystal;
 mod enemies;
-use enemies::*;
 mod history;
 use history::*;
+mod input;
+use input::*;
+mod controls;
+mod gamepad;
+mod level;
+mod menu;
+use level::*;
+mod tiled;
+mod world;
+use world::{Progress, World};
+mod ending;
+mod game;
+mod health;
+mod hud;
+mod interact;
+mod inventory;
+mod pause;
+mod pickup;
+mod scene;
+mod settings;
+mod skill;
+use scene::{Context, Stack};
 
 #[macroquad::main("GameJaaj7")]
 async fn main() {
-    let back = load_texture("assets/menu/loading.png").await.unwrap();
-    let text = load_texture("assets/menu/loading_textures.png")
-        .await
-        .unwrap();
-
-    draw_texture(back, 0., 0., WHITE);
-    draw_texture(text, 0., 0., WHITE);
-
-    let textures = Textures::get().await;
-    let mut floors =
-        Map::from_file("assets/world-data/floors.txt", 15. * 6., false, &textures).await;
-    let mut walls = Map::from_file("assets/world-data/walls.txt", 15. * 6., true, &textures).await;
-    let mut player = Player::new(
-        Vec2::new(
-            14. * walls.size + walls.size / 2.,
-            12. * walls.size + walls.size / 2.,
-        ),
-        &textures,
-        2.,
-        8.,
-    );
-    let real_size = vec![
-        player.sprite.animations[player.sprite.cur_animation].width as f32,
-        player.size * player.sprite.animations[player.sprite.cur_animation].height as f32,
-    ];
-    player.pos.x -= real_size[0];
-    player.pos.y -= real_size[1] / 2.;
-    let mut enemies = Enemies {
-        corujas: vec![Coruja::new(
-            Vec2::new(
-                14. * walls.size + walls.size / 2.,
-                12. * walls.size + walls.size / 2.,
-            ),
-            &textures,
-            2.,
-            8.,
-        )],
-    };
-    let mut camera = Camera {
-        pos: Vec2::new(90., 90.),
-        zoom: 1.,
-        speed: Vec2::new(0., 0.),
-        speed_limit: Vec2::new(50., 20.),
-    };
-    let mut kind = 1;
-    let mut wall = false;
-    let mut scene = 0;
-    let mut exit = false;
-
-    let mut history = History::new(&textures);
-    let mut debug = false;
-
-    loop {
-        match scene {
-            0 => history.tick(&mut scene),
-            1 => menu(&textures, &mut scene, &mut exit),
-            2 => in_game(
-                &mut kind,
-                &mut walls,
-                &mut floors,
-                &mut camera,
-                &textures,
-                &mut wall,
-                &mut player,
-                &mut enemies,
-                &mut debug,
-            ),
-            _ => (),
-        };
-
-        if exit {
-            break;
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() == 5 && args[1] == "--convert-map" {
+        match convert_map(&args[2], &args[3], &args[4]).await {
+            Ok(()) => println!("wrote {}", args[4]),
+            Err(err) => println!("{}", err),
         }
-        next_frame().await
+        return;
     }
-}
-
-fn menu(textures: &Textures, scene: &mut i32, exit: &mut bool) {
-    draw_texture(textures.menu_art, 0., 0., WHITE);
 
-    let new_pos = Vec2::new(500., 200.);
-    let new_x = mouse_position().0 - new_pos.x;
-    let new_y = mouse_position().1 - new_pos.y;
-    let new_in = new_x < textures.new_game.width()
-        && new_x > 0.
-        && new_y > 0.
-        && new_y < textures.new_game.height();
-    draw_texture(
-        textures.new_game,
-        new_pos.x,
-        new_pos.y,
-        if new_in { LIGHTGRAY } else { WHITE },
-    );
+    let bindings = Bindings::from_file(BINDINGS_PATH).await;
 
-    let exit_pos = Vec2::new(
-        new_pos.x + textures.new_game.width() / 2. - textures.exit.width() / 2.,
-        280.,
-    );
-    let exit_x = mouse_position().0 - exit_pos.x;
-    let exit_y = mouse_position().1 - exit_pos.y;
-    let exit_in = exit_x < textures.exit.width()
-        && exit_x > 0.
-        && exit_y > 0.
-        && exit_y < textures.exit.height();
-    draw_texture(
-        textures.exit,
-        exit_pos.x,
-        exit_pos.y,
-        if exit_in { LIGHTGRAY } else { WHITE },
-    );
+    //debug builds boot with placeholder art instead of stopping on a missing file
+    let mut loader = Loader::new(cfg!(debug_assertions));
+    let back = loader.texture("assets/menu/loading.png").await;
+    let text = loader.texture("assets/menu/loading_textures.png").await;
 
-    if new_in && is_mouse_button_pressed(MouseButton::Left) {
-        *scene += 1;
-    } else if exit_in && is_mouse_button_pressed(MouseButton::Left) {
-        *exit = true;
-    }
-}
-
-fn edit_map(
-    kind: &mut i8,
-    walls: &mut Map,
-    floors: &mut Map,
-    camera: &mut Camera,
-    textures: &Textures,
-    wall: &mut bool,
-) {
-    let x = (((mouse_position().0 - camera.pos.x) / camera.zoom) / walls.size) as usize;
-    let y = (((mouse_position().1 - camera.pos.y) / camera.zoom) / walls.size) as usize;
-    let map = if *wall { walls } else { floors };
+    draw_texture(back, 0., 0., WHITE);
+    draw_texture(text, 0., 0., WHITE);
 
-    if x < map.width && y < map.height {
-        if is_mouse_button_down(MouseButton::Left) {
-            map.vec[y][x].kind = *kind as u8;
-        } else if is_mouse_button_down(MouseButton::Right) {
-            map.vec[y][x].kind = 0;
+    let assets = Assets::load(&mut loader, MANIFEST_PATH).await;
+    let audio = Audio::load(&mut loader, MANIFEST_PATH).await;
+    let (assets, mut audio) = match loader.finish((assets, audio)) {
+        Ok(loaded) => loaded,
+        Err(err) => return fatal(&err.to_string(), &bindings).await,
+    };
+    audio.load_volume(VOLUME_PATH).await;
+
+    if args.len() == 4 && args[1] == "--import-tiled" {
+        match tiled::import(&args[2], &assets.defs).await {
+            Ok(level) => match level.to_file(&args[3]) {
+                Ok(()) => println!("wrote {}", args[3]),
+                Err(err) => println!("{}: {}", args[3], err),
+            },
+            Err(err) => println!("{}", err),
         }
+        return;
     }
 
-    if is_key_pressed(KeyCode::P) {
-        map.to_file();
-    }
-    if is_key_pressed(KeyCode::F) {
-        *wall = !*wall;
-    }
-
-    let scroll = if mouse_wheel().1 > 0. {
-        1.
-    } else if mouse_wheel().1 < 0. {
-        -1.
-    } else {
-        0.
+    //`--level path` plays another level, either our JSON or a Tiled map
+    let level_path = match args.iter().position(|arg| arg == "--level") {
+        Some(i) if i + 1 < args.len() => args[i + 1].clone(),
+        _ => LEVEL_PATH.to_string(),
     };
-    if is_key_down(KeyCode::LeftShift) {
-        camera.zoom += scroll / 10.;
+    let level = if level_path.ends_with(".tmx") || level_path.ends_with(".tmj") {
+        tiled::import(&level_path, &assets.defs).await
     } else {
-        *kind += scroll as i8;
+        Level::from_file(&level_path).await
+    };
+    let level = match level {
+        Ok(level) => level,
+        Err(err) => return fatal(&err.to_string(), &bindings).await,
+    };
+    //build the world once so a broken level stops here instead of at New Game
+    if let Err(err) = World::new(level.clone(), &assets.defs) {
+        return fatal(&format!("{}: {}", level_path, err), &bindings).await;
     }
 
-    let n = if *wall {
-        (textures.walls.width() / (map.size / 6.)) as i8
-    } else {
-        (textures.floors.width() / (map.size / 6.)) as i8
+    let mut ctx = Context {
+        assets,
+        audio,
+        bindings,
+        level,
+        progress: Progress::default(),
     };
+    let history = History::new(&ctx.assets);
+    let mut scenes = Stack::new(Box::new(history), &mut ctx);
 
-    if *kind > n + 2 {
-        *kind = 1;
-    } else if *kind < 1 {
-        *kind = n + 2;
+    loop {
+        gamepad::update();
+        let dt = get_frame_time();
+        if !scenes.update(&mut ctx, dt) {
+            break;
+        }
+        scenes.draw(&ctx);
+        ctx.audio.update(dt);
+        next_frame().await
     }
 }
 
-fn draw_icon(kind: i8, textures: &Textures, wall: &bool, floor: &Map) {
-    if *wall {
-        let params = DrawTextureParams {
-            dest_size: Some(macroquad::prelude::Vec2::new(30., 48.)),
-            source: Some(Rect::new(kind as f32 * 15., 0., 15., 24.)),
-            ..Default::default()
-        };
-        draw_texture_ex(textures.walls, 10., 10., WHITE, params);
-    } else {
-        let n = (kind - (textures.floors.width() / 15.) as i8) as usize;
-        if n < floor.animated.len() {
-            let params = DrawTextureParams {
-                dest_size: Some(macroquad::prelude::Vec2::new(30., 30.)),
-                source: Some(Rect::new(n as f32 * 15., 0., 15., 15.)),
-                ..Default::default()
-            };
-            draw_texture_ex(
-                floor.animated[n].animations[0].texture,
-                10.,
-                10.,
-                WHITE,
-                params,
-            );
-        } else {
-            let params = DrawTextureParams {
-                dest_size: Some(macroquad::prelude::Vec2::new(30., 30.)),
-                source: Some(Rect::new(kind as f32 * 15., 0., 15., 15.)),
-                ..Default::default()
-            };
-            draw_texture_ex(textures.floors, 10., 10., WHITE, params);
+/// Shows a message that the game can't continue past until it is closed.
+async fn fatal(message: &str, bindings: &Bindings) {
+    println!("{}", message);
+    loop {
+        clear_background(BLACK);
+        for (i, line) in message.lines().enumerate() {
+            draw_text(line, 20., 40. + i as f32 * 24., 24., WHITE);
         }
+        if bindings.pressed(Action::Back) {
+            break;
+        }
+        next_frame().await
     }
 }
 
-fn in_game(
-    kind: &mut i8,
-    walls: &mut Map,
-    floors: &mut Map,
-    camera: &mut Camera,
-    textures: &Textures,
-    wall: &mut bool,
-    player: &mut Player,
-    enemies: &mut Enemies,
-    debug: &mut bool,
-) {
-    clear_background(DARKGRAY);
-
-    player.update(camera, walls, floors);
-    enemies.update(walls, floors, player, textures);
-    floors.update();
-    camera.update(player);
-    walls.update();
-    floors.draw(textures, camera);
-    walls.draw(textures, camera);
-    enemies.draw(textures, camera, walls);
-    player.draw(textures, camera, walls);
-    if *debug {
-        edit_map(kind, walls, floors, camera, textures, wall);
-        draw_icon(*kind, textures, wall, floors);
-        draw_text(&get_fps().to_string(), 10., 80., 40., WHITE);
-    }
-    if is_key_pressed(KeyCode::L) {
-        *debug = !*debug;
-    }
+async fn convert_map(floors: &str, walls: &str, out: &str) -> Result<(), LevelError> {
+    let load = |path: &str| {
+        let path = path.to_string();
+        async move {
+            macroquad::file::load_string(&path)
+                .await
+                .map_err(|_| LevelError::Io { path })
+        }
+    };
+    let level = Level::from_legacy(&load(floors).await?, floors, &load(walls).await?, walls)?;
+    level.to_file(out).map_err(|err| LevelError::Write {
+        path: out.to_string(),
+        message: err.to_string(),
+    })
 }
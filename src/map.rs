@@ -1,19 +1,76 @@
+use std::collections::HashMap;
+
 use macroquad::prelude::{draw_texture_ex, DrawTextureParams, Rect, WHITE};
-use std::fs;
-use std::io::Write;
+use serde::Deserialize;
 
 use crate::animation::*;
+use crate::assets::{Assets, Defs};
 use crate::camera::Camera;
-use crate::textures::Textures;
+use crate::collision::Aabb;
+use crate::level::Layer;
 use crate::vecs::Vec2;
 
+/// Keeps moving boxes this far from what they hit, so rounding never leaves
+/// them overlapping a wall they then can't slide along.
+const SKIN: f32 = 0.01;
+
+/// What a tile does when something runs into it.
+#[derive(Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Interaction {
+    #[default]
+    None,
+    /// Opens for good, leaving an empty tile.
+    Door,
+    /// Opens once, handing over its loot.
+    Chest,
+    /// Has something to say, given by the level.
+    Statue,
+}
+
+/// One kind of tile, from the manifest's `tiles` section. Each layer lists
+/// its kinds in order, starting from 0.
+#[derive(Deserialize, Clone)]
+pub struct TileDef {
+    pub kind: u16,
+    /// A texture id, or an animation id when `animated`.
+    pub sprite: String,
+    /// The part of the texture to draw, as `[x, y, width, height]` in
+    /// pixels; the whole texture when left out.
+    pub rect: Option<[f32; 4]>,
+    #[serde(default)]
+    pub animated: bool,
+    /// Screen pixels per texture pixel.
+    #[serde(default = "tile_scale")]
+    pub scale: f32,
+    /// Nudges the sprite, in texture pixels, from the tile's bottom left
+    /// corner, where it sits by default.
+    #[serde(default)]
+    pub offset: [f32; 2],
+    #[serde(default)]
+    pub solid: bool,
+    /// Blocks walking like a solid tile, but not things flying over it.
+    #[serde(default)]
+    pub liquid: bool,
+    #[serde(default)]
+    pub interaction: Interaction,
+    /// What the tile becomes when struck by the sword; 0 breaks it for good.
+    pub breaks_into: Option<u16>,
+    /// Played when the tile is interacted with or struck.
+    pub sound: Option<String>,
+}
+
+fn tile_scale() -> f32 {
+    6.
+}
+
 #[derive(Clone)]
 pub struct Tile {
-    pub kind: u8,
+    pub kind: u16,
 }
 
 impl Tile {
-    pub fn new(kind: u8) -> Self {
+    pub fn new(kind: u16) -> Self {
         Tile { kind }
     }
 }
@@ -23,278 +80,316 @@ pub struct Map {
     pub width: usize,
     pub height: usize,
     pub size: f32,
-    pub wall: bool,
-    pub animated: Vec<AnimatedSprite>,
-    pub chest: AnimatedSprite,
+    /// The layer's tile kinds, indexed by kind.
+    pub tiles: Vec<TileDef>,
+    /// One sprite per animated kind, shared by every tile of that kind.
+    pub sprites: HashMap<String, AnimatedSprite>,
 }
 
-impl Map {
-    pub fn new(width: usize, height: usize, size: f32, wall: bool, textures: &Textures) -> Self {
-        let vec = vec![vec![Tile::new(1); width]; height];
+/// Where a box ended up after moving through a map.
+pub struct Move {
+    /// How far it actually went, at most the asked for delta.
+    pub delta: Vec2,
+    /// The solid tiles it ran into, as `(x, y)`.
+    pub touched: Vec<(usize, usize)>,
+}
 
-        let water_1 = Animation {
-            cur_frame: 0,
-            frames: 5,
-            height: 15,
-            width: 15,
-            texture: textures.water_1,
-            rect: Rect {
-                x: 0.,
-                y: 0.,
-                w: 15.,
-                h: 15.,
-            },
-        };
-        let water_2 = Animation {
-            cur_frame: 0,
-            frames: 5,
-            height: 15,
-            width: 15,
-            texture: textures.water_2,
-            rect: Rect {
-                x: 0.,
-                y: 0.,
-                w: 15.,
-                h: 15.,
-            },
-        };
-        let water_1 = AnimatedSprite {
-            animations: vec![water_1],
-            cur_animation: 0,
-            dur: 0.3,
-            playing: true,
-            time: 0.,
-        };
-        let water_2 = AnimatedSprite {
-            animations: vec![water_2],
-            cur_animation: 0,
-            dur: 0.3,
-            playing: true,
-            time: 0.,
-        };
+impl Map {
+    /// A map of `width` by `height` tiles of kind 0, using the tile kinds of
+    /// the layer called `name`.
+    pub fn new(name: &str, width: usize, height: usize, size: f32, defs: &Defs) -> Self {
+        let vec = vec![vec![Tile::new(0); width]; height];
+        let tiles = defs.tiles(name).to_vec();
+        let mut sprites = HashMap::new();
+        for def in tiles.iter().filter(|def| def.animated) {
+            let mut sprite = AnimatedSprite::from_def(defs.animation(&def.sprite), defs);
+            //interactables only animate while being used
+            sprite.playing = def.interaction == Interaction::None;
+            sprites.insert(def.sprite.clone(), sprite);
+        }
 
-        let chest = Animation {
-            cur_frame: 0,
-            frames: 5,
-            height: 25,
-            width: 25,
-            texture: textures.chest_open,
-            rect: Rect {
-                x: 0.,
-                y: 0.,
-                w: 25.,
-                h: 25.,
-            },
-        };
-        let chest = AnimatedSprite {
-            animations: vec![chest],
-            cur_animation: 0,
-            dur: 0.3,
-            playing: false,
-            time: 0.,
-        };
         Map {
             vec,
             width,
             height,
             size,
-            wall,
-            animated: vec![water_1, water_2],
-            chest,
+            tiles,
+            sprites,
         }
     }
 
-    pub async fn from_file(path: &str, size: f32, wall: bool, textures: &Textures) -> Self {
-        let string = macroquad::file::load_string(path).await.unwrap();
-        let mut vec: Vec<Vec<Tile>> = Vec::new();
-        let mut row: Vec<Tile> = Vec::new();
-        for c in string.chars() {
-            if c.is_numeric() {
-                row.push(Tile::new(c as u8 - b'0'));
+    pub fn from_layer(layer: &Layer, size: f32, defs: &Defs) -> Self {
+        let mut map = Self::new(&layer.name, layer.width(), layer.tiles.len(), size, defs);
+        for (y, row) in layer.tiles.iter().enumerate() {
+            for (x, kind) in row.iter().enumerate() {
+                map.vec[y][x].kind = *kind;
             }
+        }
+        map
+    }
 
-            if c == '\n' {
-                vec.push(row.clone());
-                for i in (0..row.len()).rev() {
-                    row.remove(i);
+    pub fn def(&self, kind: u16) -> Option<&TileDef> {
+        self.tiles.get(kind as usize)
+    }
+
+    /// The definition of the tile at `x`, `y`, if it is inside the map.
+    pub fn def_at(&self, x: usize, y: usize) -> Option<&TileDef> {
+        let tile = self.vec.get(y).and_then(|row| row.get(x))?;
+        self.def(tile.kind)
+    }
+
+    /// Strikes every breakable tile overlapping `area`, turning each into
+    /// the next kind along, and returns the sounds they make.
+    pub fn strike(&mut self, area: &Aabb) -> Vec<String> {
+        let mut sounds = Vec::new();
+        let x1 = (area.pos.x / self.size).max(0.) as usize;
+        let y1 = (area.pos.y / self.size).max(0.) as usize;
+        let x2 = ((area.pos.x + area.size.x) / self.size).max(0.) as usize;
+        let y2 = ((area.pos.y + area.size.y) / self.size).max(0.) as usize;
+        for y in y1..=y2.min(self.height.saturating_sub(1)) {
+            for x in x1..=x2.min(self.width.saturating_sub(1)) {
+                let tile = Aabb::new(
+                    Vec2::new(x as f32 * self.size, y as f32 * self.size),
+                    Vec2::new(self.size, self.size),
+                );
+                let def = match self.def_at(x, y) {
+                    Some(def) if tile.intersects(area) => def,
+                    _ => continue,
+                };
+                if let Some(next) = def.breaks_into {
+                    sounds.extend(def.sound.clone());
+                    self.vec[y][x].kind = next;
                 }
             }
         }
-        let water_1 = Animation {
-            cur_frame: 0,
-            frames: 5,
-            height: 15,
-            width: 15,
-            texture: textures.water_1,
-            rect: Rect {
-                x: 0.,
-                y: 0.,
-                w: 15.,
-                h: 15.,
-            },
-        };
-        let water_2 = Animation {
-            cur_frame: 0,
-            frames: 5,
-            height: 15,
-            width: 15,
-            texture: textures.water_2,
-            rect: Rect {
-                x: 0.,
-                y: 0.,
-                w: 15.,
-                h: 15.,
-            },
-        };
-        let water_1 = AnimatedSprite {
-            animations: vec![water_1],
-            cur_animation: 0,
-            dur: 0.3,
-            playing: true,
-            time: 0.,
-        };
-        let water_2 = AnimatedSprite {
-            animations: vec![water_2],
-            cur_animation: 0,
-            dur: 0.3,
-            playing: true,
-            time: 0.,
-        };
+        sounds
+    }
 
-        let chest = Animation {
-            cur_frame: 0,
-            frames: 5,
-            height: 25,
-            width: 25,
-            texture: textures.chest_open,
-            rect: Rect {
-                x: 0.,
-                y: 0.,
-                w: 25.,
-                h: 25.,
-            },
-        };
-        let chest = AnimatedSprite {
-            animations: vec![chest],
-            cur_animation: 0,
-            dur: 0.3,
-            playing: false,
-            time: 0.,
-        };
-        Map {
-            width: vec[0].len(),
-            height: vec.len(),
-            vec,
-            size,
-            wall,
-            animated: vec![water_1, water_2],
-            chest,
+    /// Whether nothing solid stands on the straight line between `from` and
+    /// `to`, checked every quarter tile.
+    pub fn line_clear(&self, from: Vec2, to: Vec2) -> bool {
+        let (dx, dy) = (to.x - from.x, to.y - from.y);
+        let steps = ((dx * dx + dy * dy).sqrt() / (self.size / 4.)).ceil() as usize;
+        (0..=steps).all(|i| {
+            let t = i as f32 / steps.max(1) as f32;
+            let (x, y) = (from.x + dx * t, from.y + dy * t);
+            x < 0.
+                || y < 0.
+                || !self
+                    .def_at((x / self.size) as usize, (y / self.size) as usize)
+                    .is_some_and(|def| def.solid)
+        })
+    }
+
+    /// Whether walking into the tile at `x`, `y` is stopped. Outside the map
+    /// nothing is.
+    pub fn blocks(&self, x: usize, y: usize) -> bool {
+        self.def_at(x, y).is_some_and(|def| def.solid || def.liquid)
+    }
+
+    /// Moves `aabb` by `delta` one axis at a time, so it slides along walls
+    /// instead of stopping dead. Every tile the box passes over is checked,
+    /// however far it goes. Tiles it already overlaps are ignored, letting
+    /// it walk out of a wall it was placed in.
+    pub fn move_box(&self, aabb: &Aabb, delta: Vec2) -> Move {
+        let mut touched = Vec::new();
+        let x = self.clip(aabb, Vec2::new(delta.x, 0.), &mut touched);
+        let moved = Aabb::new(Vec2::new(aabb.pos.x + x, aabb.pos.y), aabb.size);
+        let y = self.clip(&moved, Vec2::new(0., delta.y), &mut touched);
+        Move {
+            delta: Vec2::new(x, y),
+            touched,
         }
     }
 
-    pub fn to_file(&self) {
-        let mut map = String::new();
-        for row in &self.vec {
-            for tile in row {
-                map += &tile.kind.to_string();
-            }
-            map += "\n";
+    /// How far `aabb` gets along `delta`, which points along a single axis.
+    fn clip(&self, aabb: &Aabb, delta: Vec2, touched: &mut Vec<(usize, usize)>) -> f32 {
+        let len = delta.x + delta.y;
+        if len == 0. {
+            return 0.;
         }
-        println!("{}", map);
 
-        let path = if self.wall {
-            "assets/world-data/walls.txt"
-        } else {
-            "assets/world-data/floors.txt"
-        };
-        let mut output = fs::File::create(path).unwrap();
-        write!(output, "{}", map).unwrap();
+        //the tiles under the whole path, not just where it ends
+        let tile = |value: f32| (value / self.size).floor().max(0.) as usize;
+        let x1 = tile(aabb.pos.x.min(aabb.pos.x + delta.x));
+        let y1 = tile(aabb.pos.y.min(aabb.pos.y + delta.y));
+        let x2 = tile(aabb.pos.x.max(aabb.pos.x + delta.x) + aabb.size.x);
+        let y2 = tile(aabb.pos.y.max(aabb.pos.y + delta.y) + aabb.size.y);
+
+        let mut first = 1.;
+        let mut hits = Vec::new();
+        for y in y1..=y2.min(self.height.saturating_sub(1)) {
+            for x in x1..=x2.min(self.width.saturating_sub(1)) {
+                if !self.blocks(x, y) {
+                    continue;
+                }
+                let pos = Vec2::new(x as f32 * self.size, y as f32 * self.size);
+                let tile = Aabb::new(pos, Vec2::new(self.size, self.size));
+                if aabb.intersects(&tile) {
+                    continue;
+                }
+                if let Some(t) = aabb.sweep(delta, &tile) {
+                    if t < first {
+                        first = t;
+                        hits.clear();
+                    }
+                    if t == first {
+                        hits.push((x, y));
+                    }
+                }
+            }
+        }
+        if hits.is_empty() {
+            return len;
+        }
+        touched.extend(hits);
+        let allowed = (len.abs() * first - SKIN).max(0.);
+        allowed * len.signum()
     }
 
-    pub fn draw(&self, textures: &Textures, camera: &Camera) {
-        let n_floors = (textures.floors.width() / 15.) as u8;
-        let n_walls = (textures.walls.width() / 15.) as u8;
+    pub fn to_layer(&self, name: &str) -> Layer {
+        Layer {
+            name: name.to_string(),
+            tiles: self
+                .vec
+                .iter()
+                .map(|row| row.iter().map(|tile| tile.kind).collect())
+                .collect(),
+        }
+    }
 
+    pub fn draw(&self, assets: &Assets, camera: &Camera) {
         for (y, row) in self.vec.iter().enumerate() {
             for (x, tile) in row.iter().enumerate() {
-                if self.wall && tile.kind < n_walls {
-                    let params = DrawTextureParams {
-                        dest_size: Some(macroquad::prelude::Vec2::new(
-                            self.size * camera.zoom,
-                            (self.size + 9. * 6.) * camera.zoom,
-                        )),
-                        source: Some(Rect::new(tile.kind as f32 * 15., 0., 15., 24.)),
-                        ..Default::default()
-                    };
+                //animated interactables have their own sprites and are
+                //drawn with them
+                if self
+                    .def(tile.kind)
+                    .is_some_and(|def| def.animated && def.interaction != Interaction::None)
+                {
+                    continue;
+                }
+                let pos = Vec2::new(x as f32 * self.size, y as f32 * self.size);
+                self.draw_tile(tile.kind, pos, assets, camera);
+            }
+        }
+    }
 
-                    draw_texture_ex(
-                        textures.walls,
-                        x as f32 * self.size * camera.zoom + camera.pos.x,
-                        (y as f32 * self.size - 9. * 6.) * camera.zoom + camera.pos.y,
-                        WHITE,
-                        params,
-                    );
-                } else if self.wall {
-                    let kind = (tile.kind - n_walls) as usize;
-                    if kind == 0 {
-                        let pos = Vec2::new(x as f32 * self.size, y as f32 * self.size);
-                        self.chest.draw(&pos, &3.6, &false, camera);
-                    } else if kind == 1 {
-                        let size = 15. * 4.;
-                        let pos = Vec2::new(
-                            x as f32 * self.size + size / 4.,
-                            y as f32 * self.size - size / 1.9,
-                        );
-                        let params = DrawTextureParams {
-                            dest_size: Some(macroquad::prelude::Vec2::new(
-                                size * camera.zoom,
-                                size * 1.9 * camera.zoom,
-                            )),
-                            ..Default::default()
-                        };
+    /// Draws a tile of `kind` in the tile whose top left corner is `pos`.
+    pub fn draw_tile(&self, kind: u16, pos: Vec2, assets: &Assets, camera: &Camera) {
+        self.draw_tile_with(kind, None, pos, assets, camera);
+    }
 
-                        draw_texture_ex(
-                            textures.statue,
-                            pos.x * camera.zoom + camera.pos.x,
-                            pos.y * camera.zoom + camera.pos.y,
-                            WHITE,
-                            params,
-                        );
-                    }
-                } else if !self.wall && tile.kind < n_floors {
-                    let params = DrawTextureParams {
-                        dest_size: Some(macroquad::prelude::Vec2::new(
-                            self.size * camera.zoom,
-                            self.size * camera.zoom,
-                        )),
-                        source: Some(Rect::new(tile.kind as f32 * 15., 0., 15., 15.)),
-                        ..Default::default()
-                    };
+    /// Like `draw_tile`, but animated kinds use `sprite` when given instead
+    /// of the map's shared one.
+    pub fn draw_tile_with(
+        &self,
+        kind: u16,
+        sprite: Option<&AnimatedSprite>,
+        pos: Vec2,
+        assets: &Assets,
+        camera: &Camera,
+    ) {
+        let def = match self.def(kind) {
+            Some(def) => def,
+            None => return,
+        };
+        let offset = |height: f32| {
+            Vec2::new(
+                pos.x + def.offset[0] * def.scale,
+                pos.y + self.size - height * def.scale + def.offset[1] * def.scale,
+            )
+        };
 
-                    draw_texture_ex(
-                        textures.floors,
-                        (x as f32 * self.size) * camera.zoom + camera.pos.x,
-                        (y as f32 * self.size) * camera.zoom + camera.pos.y,
-                        WHITE,
-                        params,
-                    );
-                } else if !self.wall {
-                    let kind = (tile.kind - n_floors) as usize;
+        if def.animated {
+            let sprite = sprite.unwrap_or(&self.sprites[&def.sprite]);
+            let height = sprite.animations[sprite.cur_animation].height as f32;
+            sprite.draw(&offset(height), &def.scale, &false, assets, camera);
+            return;
+        }
 
-                    if kind < self.animated.len() {
-                        let pos = Vec2::new(x as f32 * self.size, y as f32 * self.size);
-                        self.animated[kind].draw(&pos, &6., &false, camera);
-                    }
+        let texture = assets.texture(&def.sprite);
+        let rect = match def.rect {
+            Some([x, y, w, h]) => Rect::new(x, y, w, h),
+            None => Rect::new(0., 0., texture.width(), texture.height()),
+        };
+        let pos = offset(rect.h);
+        let params = DrawTextureParams {
+            dest_size: Some(macroquad::prelude::Vec2::new(
+                rect.w * def.scale * camera.zoom,
+                rect.h * def.scale * camera.zoom,
+            )),
+            source: Some(rect),
+            ..Default::default()
+        };
+        draw_texture_ex(
+            texture,
+            pos.x * camera.zoom + camera.pos.x,
+            pos.y * camera.zoom + camera.pos.y,
+            WHITE,
+            params,
+        );
+    }
+
+    /// Redraws the solid sheet tiles around something standing at `pos`,
+    /// `height` tall, so it shows between their bottom halves, drawn before
+    /// it, and their top halves, drawn after it with `front`.
+    pub fn draw_near(
+        &self,
+        pos: &Vec2,
+        height: f32,
+        front: bool,
+        assets: &Assets,
+        camera: &Camera,
+    ) {
+        let x0 = (pos.x / self.size) as usize;
+        let y0 = ((pos.y + height) / self.size) as usize;
+        for y in y0.saturating_sub(1)..y0 + 2 {
+            for x in x0.saturating_sub(1)..x0 + 2 {
+                if front {
+                    self.draw_half(x, y, true, assets, camera);
+                    self.draw_half(x, y + 1, true, assets, camera);
+                } else {
+                    self.draw_half(x, y, false, assets, camera);
                 }
             }
         }
     }
 
-    pub fn update(&mut self) {
-        for sprite in &mut self.animated {
-            sprite.update();
-        }
+    fn draw_half(&self, x: usize, y: usize, top: bool, assets: &Assets, camera: &Camera) {
+        let def = match self.def_at(x, y) {
+            Some(def) if def.solid && !def.animated => def,
+            _ => return,
+        };
+        let rect = match def.rect {
+            Some([x, y, w, h]) => Rect::new(x, y + if top { 0. } else { h / 2. }, w, h / 2.),
+            None => return,
+        };
+        let full = rect.h * 2. * def.scale;
+        let pos = Vec2::new(
+            x as f32 * self.size,
+            y as f32 * self.size + self.size - full + if top { 0. } else { full / 2. },
+        );
+        let params = DrawTextureParams {
+            dest_size: Some(macroquad::prelude::Vec2::new(
+                rect.w * def.scale * camera.zoom,
+                (full / 2.) * camera.zoom,
+            )),
+            source: Some(rect),
+            ..Default::default()
+        };
+        draw_texture_ex(
+            assets.texture(&def.sprite),
+            pos.x * camera.zoom + camera.pos.x,
+            pos.y * camera.zoom + camera.pos.y,
+            WHITE,
+            params,
+        );
+    }
 
-        self.chest.update();
+    pub fn update(&mut self, dt: f32) {
+        for sprite in self.sprites.values_mut() {
+            sprite.update(dt);
+        }
     }
 }
@@ -0,0 +1,176 @@
+use macroquad::prelude::{
+    draw_rectangle, draw_text, draw_texture, is_mouse_button_pressed, measure_text, mouse_position,
+    screen_height, screen_width, Color, MouseButton, Texture2D, LIGHTGRAY, WHITE, YELLOW,
+};
+
+use crate::assets::Assets;
+use crate::collision::Aabb;
+use crate::controls::Controls;
+use crate::game::Game;
+use crate::input::Action;
+use crate::scene::{Context, Scene, Transition};
+use crate::vecs::Vec2;
+use crate::world::Progress;
+
+enum Label {
+    Texture(Texture2D),
+    Text(&'static str),
+}
+
+struct Button {
+    label: Label,
+    pos: Vec2,
+    size: Vec2,
+}
+
+impl Button {
+    fn contains(&self, point: (f32, f32)) -> bool {
+        Aabb::new(self.pos, self.size).contains(Vec2::new(point.0, point.1))
+    }
+
+    fn draw(&self, selected: bool) {
+        let color = if selected { LIGHTGRAY } else { WHITE };
+        match self.label {
+            Label::Texture(texture) => draw_texture(texture, self.pos.x, self.pos.y, color),
+            Label::Text(text) => {
+                let offset = measure_text(text, None, 40, 1.).offset_y;
+                draw_text(text, self.pos.x, self.pos.y + offset, 40., color);
+            }
+        }
+    }
+}
+
+/// Main menu, navigable with the mouse or with the move actions (D-pad,
+/// arrows) plus `Confirm`. Clicking picks the button under the mouse.
+pub struct Menu {
+    pub selected: Option<usize>,
+    pub last_mouse: (f32, f32),
+}
+
+impl Menu {
+    pub fn new() -> Self {
+        Self {
+            selected: None,
+            last_mouse: mouse_position(),
+        }
+    }
+
+    fn buttons(assets: &Assets) -> [Button; 3] {
+        let new_pos = Vec2::new(500., 200.);
+        let center = new_pos.x + assets.texture("new_game").width() / 2.;
+        let exit_pos = Vec2::new(center - assets.texture("exit").width() / 2., 280.);
+        let controls_size = measure_text("Controls", None, 40, 1.);
+
+        [
+            Button {
+                label: Label::Texture(assets.texture("new_game")),
+                pos: new_pos,
+                size: Vec2::new(
+                    assets.texture("new_game").width(),
+                    assets.texture("new_game").height(),
+                ),
+            },
+            Button {
+                label: Label::Texture(assets.texture("exit")),
+                pos: exit_pos,
+                size: Vec2::new(
+                    assets.texture("exit").width(),
+                    assets.texture("exit").height(),
+                ),
+            },
+            Button {
+                label: Label::Text("Controls"),
+                pos: Vec2::new(
+                    center - controls_size.width / 2.,
+                    exit_pos.y + assets.texture("exit").height() + 20.,
+                ),
+                size: Vec2::new(controls_size.width, controls_size.height),
+            },
+        ]
+    }
+}
+
+impl Scene for Menu {
+    fn update(&mut self, ctx: &mut Context, _dt: f32) -> Transition {
+        let buttons = Self::buttons(&ctx.assets);
+        let selected = self.selected;
+
+        let mouse = mouse_position();
+        if mouse != self.last_mouse {
+            self.last_mouse = mouse;
+            self.selected = buttons.iter().position(|button| button.contains(mouse));
+        }
+
+        if ctx.bindings.pressed(Action::MoveDown) {
+            self.selected = Some(self.selected.map_or(0, |i| (i + 1) % buttons.len()));
+        } else if ctx.bindings.pressed(Action::MoveUp) {
+            self.selected = Some(
+                self.selected
+                    .map_or(0, |i| (i + buttons.len() - 1) % buttons.len()),
+            );
+        }
+
+        if self.selected.is_some() && self.selected != selected {
+            ctx.audio.play("button");
+        }
+
+        let clicked = is_mouse_button_pressed(MouseButton::Left)
+            && self.selected.is_some_and(|i| buttons[i].contains(mouse));
+        if !ctx.bindings.pressed(Action::Confirm) && !clicked {
+            return Transition::None;
+        }
+        if self.selected.is_some() {
+            ctx.audio.play("confirm");
+        }
+        match self.selected {
+            Some(0) => {
+                ctx.progress = Progress::default();
+                Transition::Switch(Box::new(Game::new(ctx)))
+            }
+            Some(1) => Transition::Quit,
+            Some(2) => Transition::Push(Box::new(Controls::new())),
+            _ => Transition::None,
+        }
+    }
+
+    fn draw(&self, ctx: &Context) {
+        draw_texture(ctx.assets.texture("menu_art"), 0., 0., WHITE);
+        for (i, button) in Self::buttons(&ctx.assets).iter().enumerate() {
+            button.draw(self.selected == Some(i));
+        }
+    }
+
+    fn music(&self) -> Option<&str> {
+        Some("menu")
+    }
+}
+
+/// Moves `cur` through a list of `len` options with the move actions.
+pub fn navigate(ctx: &Context, cur: &mut usize, len: usize) {
+    if ctx.bindings.pressed(Action::MoveDown) {
+        *cur = (*cur + 1) % len;
+        ctx.audio.play("button");
+    } else if ctx.bindings.pressed(Action::MoveUp) {
+        *cur = (*cur + len - 1) % len;
+        ctx.audio.play("button");
+    }
+}
+
+/// A title over a dimmed screen and a column of options, the selected one
+/// highlighted.
+pub fn draw_options(title: &str, options: &[String], cur: usize) {
+    draw_rectangle(
+        0.,
+        0.,
+        screen_width(),
+        screen_height(),
+        Color::new(0., 0., 0., 0.6),
+    );
+    let x = screen_width() / 2. - 150.;
+    let y = screen_height() / 2. - options.len() as f32 * 25.;
+    draw_text(title, x, y - 30., 50., WHITE);
+    for (i, option) in options.iter().enumerate() {
+        let color = if i == cur { YELLOW } else { WHITE };
+        draw_text(option, x, y + 20. + i as f32 * 40., 36., color);
+    }
+}
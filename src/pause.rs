@@ -0,0 +1,53 @@
+use crate::game::Game;
+use crate::input::Action;
+use crate::menu::{draw_options, navigate, Menu};
+use crate::scene::{Context, Scene, Transition};
+use crate::settings::Settings;
+use crate::world::Progress;
+
+const OPTIONS: [&str; 4] = ["Resume", "Settings", "Restart", "Quit to menu"];
+
+/// Drawn over the game, which stays frozen underneath since only the top
+/// scene updates.
+pub struct Pause {
+    pub cur: usize,
+}
+
+impl Pause {
+    pub fn new() -> Self {
+        Self { cur: 0 }
+    }
+}
+
+impl Scene for Pause {
+    fn update(&mut self, ctx: &mut Context, _dt: f32) -> Transition {
+        if ctx.bindings.pressed(Action::Pause) || ctx.bindings.pressed(Action::Back) {
+            ctx.audio.play("cancel");
+            return Transition::Pop;
+        }
+        navigate(ctx, &mut self.cur, OPTIONS.len());
+        if !ctx.bindings.pressed(Action::Confirm) {
+            return Transition::None;
+        }
+
+        ctx.audio.play("confirm");
+        match self.cur {
+            0 => Transition::Pop,
+            1 => Transition::Push(Box::new(Settings::new())),
+            2 => {
+                ctx.progress = Progress::default();
+                Transition::Switch(Box::new(Game::new(ctx)))
+            }
+            _ => Transition::Switch(Box::new(Menu::new())),
+        }
+    }
+
+    fn draw(&self, _ctx: &Context) {
+        let options: Vec<String> = OPTIONS.iter().map(|option| option.to_string()).collect();
+        draw_options("Paused", &options, self.cur);
+    }
+
+    fn overlay(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,43 @@
+use macroquad::prelude::{
+    draw_rectangle, draw_texture, screen_height, screen_width, Color, Texture2D, WHITE,
+};
+
+use crate::input::Action;
+use crate::scene::{Context, Scene, Transition};
+
+/// The picture for a newly found item, drawn over the game, which waits
+/// underneath until it is closed with `Confirm`.
+pub struct Pickup {
+    pub texture: Texture2D,
+}
+
+impl Pickup {
+    pub fn new(texture: Texture2D) -> Self {
+        Self { texture }
+    }
+}
+
+impl Scene for Pickup {
+    fn update(&mut self, ctx: &mut Context, _dt: f32) -> Transition {
+        if ctx.bindings.pressed(Action::Confirm) {
+            ctx.audio.play("confirm");
+            return Transition::Pop;
+        }
+        Transition::None
+    }
+
+    fn draw(&self, _ctx: &Context) {
+        let back = Color::new(0., 0., 0., 0.5);
+        draw_rectangle(0., 0., screen_width(), screen_height(), back);
+        draw_texture(
+            self.texture,
+            (screen_width() - self.texture.width()) / 2.,
+            (screen_height() - self.texture.height()) / 2.,
+            WHITE,
+        );
+    }
+
+    fn overlay(&self) -> bool {
+        true
+    }
+}
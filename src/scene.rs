@@ -0,0 +1,128 @@
+use macroquad::prelude::{draw_rectangle, screen_height, screen_width, Color};
+
+use crate::assets::Assets;
+use crate::audio::Audio;
+use crate::input::Bindings;
+use crate::level::Level;
+use crate::world::Progress;
+
+/// Seconds for each half of the fade between two scenes.
+const FADE: f32 = 0.3;
+
+/// State every scene shares and that outlives any one of them.
+pub struct Context {
+    pub assets: Assets,
+    pub audio: Audio,
+    pub bindings: Bindings,
+    /// The level new games start from.
+    pub level: Level,
+    /// Kept across deaths, cleared by a new game or a restart.
+    pub progress: Progress,
+}
+
+/// What the stack should do after a scene's update.
+pub enum Transition {
+    None,
+    /// Puts a scene on top, like a pause menu over the game.
+    Push(Box<dyn Scene>),
+    /// Removes the top scene, going back to the one under it.
+    Pop,
+    /// Fades out, drops every scene on the stack and fades into this one.
+    Switch(Box<dyn Scene>),
+    Quit,
+}
+
+pub trait Scene {
+    fn enter(&mut self, _ctx: &mut Context) {}
+    fn update(&mut self, ctx: &mut Context, dt: f32) -> Transition;
+    fn draw(&self, ctx: &Context);
+    fn exit(&mut self, _ctx: &mut Context) {}
+
+    /// Whether the scene below keeps being drawn underneath this one.
+    fn overlay(&self) -> bool {
+        false
+    }
+
+    /// The music track for this scene, or `None` to keep the one below it.
+    fn music(&self) -> Option<&str> {
+        None
+    }
+}
+
+/// Only the top scene is updated; it and any overlays under it are drawn.
+pub struct Stack {
+    pub scenes: Vec<Box<dyn Scene>>,
+    /// Waits for the fade out to finish before switching.
+    next: Option<Box<dyn Scene>>,
+    //0 is clear, 1 is black
+    fade: f32,
+}
+
+impl Stack {
+    pub fn new(mut scene: Box<dyn Scene>, ctx: &mut Context) -> Self {
+        scene.enter(ctx);
+        Self {
+            scenes: vec![scene],
+            next: None,
+            fade: 1.,
+        }
+    }
+
+    /// Returns false once the game should close.
+    pub fn update(&mut self, ctx: &mut Context, dt: f32) -> bool {
+        if self.next.is_some() {
+            self.fade += dt / FADE;
+            if self.fade >= 1. {
+                self.fade = 1.;
+                for mut scene in self.scenes.drain(..).rev() {
+                    scene.exit(ctx);
+                }
+                let mut scene = self.next.take().unwrap();
+                scene.enter(ctx);
+                self.scenes.push(scene);
+            }
+            return true;
+        }
+        self.fade = (self.fade - dt / FADE).max(0.);
+
+        let scene = match self.scenes.last_mut() {
+            Some(scene) => scene,
+            None => return false,
+        };
+        match scene.update(ctx, dt) {
+            Transition::None => (),
+            Transition::Push(mut scene) => {
+                scene.enter(ctx);
+                self.scenes.push(scene);
+            }
+            Transition::Pop => {
+                if let Some(mut scene) = self.scenes.pop() {
+                    scene.exit(ctx);
+                }
+            }
+            Transition::Switch(scene) => self.next = Some(scene),
+            Transition::Quit => return false,
+        }
+
+        if let Some(music) = self.scenes.iter().rev().find_map(|scene| scene.music()) {
+            ctx.audio.play_music(music);
+        }
+        !self.scenes.is_empty()
+    }
+
+    pub fn draw(&self, ctx: &Context) {
+        let bottom = self
+            .scenes
+            .iter()
+            .rposition(|scene| !scene.overlay())
+            .unwrap_or(0);
+        for scene in &self.scenes[bottom..] {
+            scene.draw(ctx);
+        }
+
+        if self.fade > 0. {
+            let color = Color::new(0., 0., 0., self.fade);
+            draw_rectangle(0., 0., screen_width(), screen_height(), color);
+        }
+    }
+}
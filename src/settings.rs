@@ -0,0 +1,89 @@
+use macroquad::prelude::{draw_text, screen_height, RED};
+
+use crate::audio::VOLUME_PATH;
+use crate::controls::Controls;
+use crate::input::Action;
+use crate::menu::{draw_options, navigate};
+use crate::scene::{Context, Scene, Transition};
+
+/// Volume sliders and the way into the controls screen. Volumes are saved
+/// on the way out.
+pub struct Settings {
+    pub cur: usize,
+    /// Why the volumes couldn't be saved, shown until the player leaves
+    /// without them.
+    pub error: Option<String>,
+}
+
+impl Settings {
+    pub fn new() -> Self {
+        Self {
+            cur: 0,
+            error: None,
+        }
+    }
+}
+
+impl Scene for Settings {
+    fn update(&mut self, ctx: &mut Context, _dt: f32) -> Transition {
+        if ctx.bindings.pressed(Action::Back) {
+            ctx.audio.play("cancel");
+            if self.error.is_none() {
+                if let Err(err) = ctx.audio.save_volume(VOLUME_PATH) {
+                    self.error = Some(format!(
+                        "Could not save the volumes: {}. Back again to leave.",
+                        err
+                    ));
+                    return Transition::None;
+                }
+            }
+            return Transition::Pop;
+        }
+        navigate(ctx, &mut self.cur, 3);
+
+        let step = if ctx.bindings.pressed(Action::MoveRight) {
+            0.1
+        } else if ctx.bindings.pressed(Action::MoveLeft) {
+            -0.1
+        } else {
+            0.
+        };
+        let volume = match self.cur {
+            0 => &mut ctx.audio.music_volume,
+            1 => &mut ctx.audio.sfx_volume,
+            _ => {
+                if ctx.bindings.pressed(Action::Confirm) {
+                    ctx.audio.play("confirm");
+                    return Transition::Push(Box::new(Controls::new()));
+                }
+                return Transition::None;
+            }
+        };
+        if step != 0. {
+            //rounded so repeated steps don't drift away from tenths
+            *volume = ((*volume + step).clamp(0., 1.) * 10.).round() / 10.;
+            ctx.audio.play("button");
+        }
+        Transition::None
+    }
+
+    fn draw(&self, ctx: &Context) {
+        let bar = |volume: f32| {
+            let filled = (volume * 10.).round() as usize;
+            format!("{}{}", "|".repeat(filled), ".".repeat(10 - filled))
+        };
+        let options = [
+            format!("Music  < {} >", bar(ctx.audio.music_volume)),
+            format!("Sounds < {} >", bar(ctx.audio.sfx_volume)),
+            "Controls".to_string(),
+        ];
+        draw_options("Settings", &options, self.cur);
+        if let Some(error) = &self.error {
+            draw_text(error, 40., screen_height() - 40., 22., RED);
+        }
+    }
+
+    fn overlay(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,152 @@
+use macroquad::prelude::{draw_circle, draw_circle_lines, Color};
+
+use crate::animation::AnimatedSprite;
+use crate::assets::{Assets, Defs};
+use crate::camera::Camera;
+use crate::collision::Circle;
+use crate::map::Map;
+use crate::vecs::Vec2;
+
+/// Seconds between two casts.
+pub const COOLDOWN: f32 = 1.5;
+pub const MAX_MANA: f32 = 3.;
+const COST: f32 = 1.;
+/// Mana back per second.
+const REGEN: f32 = 0.25;
+/// Pixels per second.
+const SPEED: f32 = 720.;
+/// Seconds a projectile flies before going off on its own.
+const LIFETIME: f32 = 1.;
+pub const RADIUS: f32 = 130.;
+pub const DAMAGE: i32 = 2;
+/// Seconds an explosion stays on screen.
+const BLAST_TIME: f32 = 0.3;
+
+/// The player's spell: a ball of light that bursts on whatever it hits,
+/// hurting everything around it.
+pub struct Skill {
+    /// Seconds until the next cast.
+    pub cooldown: f32,
+    pub mana: f32,
+    pub projectiles: Vec<Projectile>,
+    pub explosions: Vec<Explosion>,
+    sprite: AnimatedSprite,
+}
+
+pub struct Projectile {
+    pub pos: Vec2,
+    pub velocity: Vec2,
+    pub sprite: AnimatedSprite,
+    pub time: f32,
+    /// Set when it hits something, so it bursts on the next update.
+    pub die: bool,
+}
+
+pub struct Explosion {
+    pub center: Vec2,
+    pub time: f32,
+    /// Whether enemies have taken its damage yet.
+    pub dealt: bool,
+}
+
+impl Skill {
+    pub fn new(defs: &Defs) -> Self {
+        Self {
+            cooldown: 0.,
+            mana: MAX_MANA,
+            projectiles: Vec::new(),
+            explosions: Vec::new(),
+            sprite: AnimatedSprite::from_def(defs.animation("skill"), defs),
+        }
+    }
+
+    /// Fires from `center` towards `dir`, if the cooldown is over and there is
+    /// mana for it.
+    pub fn cast(&mut self, center: Vec2, dir: Vec2) -> bool {
+        if self.cooldown > 0. || self.mana < COST {
+            return false;
+        }
+        self.cooldown = COOLDOWN;
+        self.mana -= COST;
+        let pos = Vec2::new(center.x - 21., center.y - 21.);
+        self.projectiles.push(Projectile {
+            pos,
+            velocity: Vec2::new(dir.x * SPEED, dir.y * SPEED),
+            sprite: self.sprite.clone(),
+            time: 0.,
+            die: false,
+        });
+        true
+    }
+
+    pub fn update(&mut self, dt: f32, walls: &Map, sounds: &mut Vec<String>) {
+        self.cooldown = (self.cooldown - dt).max(0.);
+        self.mana = (self.mana + REGEN * dt).min(MAX_MANA);
+
+        for projectile in &mut self.projectiles {
+            projectile.update(dt);
+            let center = projectile.collider().center;
+            let (x, y) = (
+                (center.x / walls.size) as usize,
+                (center.y / walls.size) as usize,
+            );
+            if projectile.time > LIFETIME || walls.blocks(x, y) {
+                projectile.die = true;
+            }
+        }
+        for projectile in self.projectiles.iter().filter(|p| p.die) {
+            sounds.push("skill_explode".to_string());
+            self.explosions.push(Explosion {
+                center: projectile.collider().center,
+                time: 0.,
+                dealt: false,
+            });
+        }
+        self.projectiles.retain(|projectile| !projectile.die);
+
+        for explosion in &mut self.explosions {
+            explosion.time += dt;
+        }
+        self.explosions
+            .retain(|explosion| explosion.time < BLAST_TIME || !explosion.dealt);
+    }
+
+    pub fn draw(&self, assets: &Assets, camera: &Camera) {
+        for projectile in &self.projectiles {
+            let flipped = projectile.velocity.x < 0.;
+            projectile
+                .sprite
+                .draw(&projectile.pos, &2., &flipped, assets, camera);
+        }
+        for explosion in &self.explosions {
+            let fade = 1. - explosion.time / BLAST_TIME;
+            let radius = RADIUS * (0.5 + explosion.time / BLAST_TIME / 2.) * camera.zoom;
+            let x = explosion.center.x * camera.zoom + camera.pos.x;
+            let y = explosion.center.y * camera.zoom + camera.pos.y;
+            draw_circle(x, y, radius, Color::new(1., 0.9, 0.5, 0.4 * fade));
+            draw_circle_lines(x, y, radius, 4., Color::new(1., 1., 0.8, fade));
+        }
+    }
+}
+
+impl Projectile {
+    /// The sprite is 21 px drawn at twice its size.
+    pub fn collider(&self) -> Circle {
+        Circle::new(Vec2::new(self.pos.x + 21., self.pos.y + 21.), 14.)
+    }
+
+    fn update(&mut self, dt: f32) {
+        self.pos = Vec2::new(
+            self.pos.x + self.velocity.x * dt,
+            self.pos.y + self.velocity.y * dt,
+        );
+        self.sprite.update(dt);
+        self.time += dt;
+    }
+}
+
+impl Explosion {
+    pub fn area(&self) -> Circle {
+        Circle::new(self.center, RADIUS)
+    }
+}
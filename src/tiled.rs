@@ -0,0 +1,777 @@
+//! Imports maps made in the Tiled editor (`.tmx` or `.tmj`) into a `Level`.
+//!
+//! Tile layers named `floors` and `walls` become the matching level layers.
+//! Tiles are matched to the manifest's tile kinds by the image their tileset
+//! uses and, for kinds cut out of a sheet with a `rect`, by which cell of
+//! that image they are. Object layers hold `player` and enemy spawns,
+//! plus `chest` and `door` objects that are stamped into the walls layer;
+//! a `loot` property (comma separated items), `locked = true` or
+//! `arena = true` on them becomes one of the level's objects.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::assets::Defs;
+use crate::level::{self, Layer, Level, LevelError, Spawn, LEVEL_VERSION};
+use crate::map::Interaction;
+
+//the top three bits of a gid are flip flags
+const GID_MASK: u32 = 0x1fff_ffff;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Target {
+    Floor,
+    Wall,
+}
+
+struct Tileset {
+    firstgid: u32,
+    image: String,
+    tilewidth: u32,
+    tileheight: u32,
+    columns: u32,
+    tilecount: u32,
+}
+
+struct TileLayer {
+    name: String,
+    width: usize,
+    data: Vec<u32>,
+}
+
+struct Object {
+    kind: String,
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+    gid: bool,
+    properties: HashMap<String, String>,
+}
+
+#[derive(Default)]
+struct TiledMap {
+    width: usize,
+    height: usize,
+    tilewidth: f32,
+    tileheight: f32,
+    tilesets: Vec<Tileset>,
+    layers: Vec<TileLayer>,
+    objects: Vec<Object>,
+}
+
+pub async fn import(path: &str, defs: &Defs) -> Result<Level, LevelError> {
+    let string = load(path).await?;
+    let map = if path.ends_with(".tmx") {
+        parse_tmx(&string, path).await?
+    } else {
+        parse_tmj(&string, path).await?
+    };
+
+    let mut level = to_level(&map, defs).map_err(|message| LevelError::Invalid {
+        path: path.to_string(),
+        message,
+    })?;
+    //the editor saves in the game's own format, next to the Tiled map
+    level.path = Path::new(path)
+        .with_extension("json")
+        .to_string_lossy()
+        .into_owned();
+    Ok(level)
+}
+
+async fn load(path: &str) -> Result<String, LevelError> {
+    macroquad::file::load_string(path)
+        .await
+        .map_err(|_| LevelError::Io {
+            path: path.to_string(),
+        })
+}
+
+fn invalid(path: &str, message: String) -> LevelError {
+    LevelError::Invalid {
+        path: path.to_string(),
+        message,
+    }
+}
+
+fn bad_gid(path: &str, layer: &str, gid: &str) -> LevelError {
+    invalid(
+        path,
+        format!("layer `{}` has an invalid tile `{}`", layer, gid),
+    )
+}
+
+/// Resolves a path written inside `base` (a tileset `source`) against the
+/// directory `base` lives in.
+fn relative(base: &str, path: &str) -> String {
+    match base.rfind('/') {
+        Some(i) => format!("{}/{}", &base[..i], path),
+        None => path.to_string(),
+    }
+}
+
+fn to_level(map: &TiledMap, defs: &Defs) -> Result<Level, String> {
+    let mut layers = Vec::new();
+    for layer in &map.layers {
+        let target = match layer.name.to_lowercase().as_str() {
+            "floors" => Target::Floor,
+            "walls" => Target::Wall,
+            _ => {
+                return Err(format!(
+                    "tile layer `{}` is not `floors` or `walls`",
+                    layer.name
+                ))
+            }
+        };
+
+        let mut kinds = Vec::with_capacity(layer.data.len());
+        for gid in &layer.data {
+            kinds.push(kind(map, *gid, target, defs)?);
+        }
+        let tiles = kinds.chunks(layer.width).map(|row| row.to_vec()).collect();
+        layers.push(Layer {
+            name: layer.name.to_lowercase(),
+            tiles,
+        });
+    }
+
+    let mut spawns = Vec::new();
+    let mut objects = Vec::new();
+    for object in &map.objects {
+        //tile objects are anchored at their bottom left corner
+        let top = if object.gid {
+            object.y - object.height
+        } else {
+            object.y
+        };
+        let x = ((object.x + object.width / 2.) / map.tilewidth) as usize;
+        let y = ((top + object.height / 2.) / map.tileheight) as usize;
+        if x >= map.width || y >= map.height {
+            return Err(format!("object `{}` is outside the map", object.kind));
+        }
+
+        let wall = match object.kind.as_str() {
+            "chest" => Some(interactive(defs, Interaction::Chest)?),
+            "door" => Some(match object.properties.get("tile") {
+                Some(tile) => tile
+                    .parse()
+                    .map_err(|_| format!("door has an invalid `tile` property `{}`", tile))?,
+                None => interactive(defs, Interaction::Door)?,
+            }),
+            _ => None,
+        };
+
+        match wall {
+            Some(kind) => {
+                let walls = layers
+                    .iter_mut()
+                    .find(|layer| layer.name == "walls")
+                    .ok_or(format!(
+                        "`{}` objects need a `walls` tile layer",
+                        object.kind
+                    ))?;
+                //layers can be smaller than the map they are in
+                *walls
+                    .tiles
+                    .get_mut(y)
+                    .and_then(|row| row.get_mut(x))
+                    .ok_or(format!(
+                        "`{}` at {},{} is outside the `walls` layer",
+                        object.kind, x, y
+                    ))? = kind;
+
+                let locked = object.properties.get("locked").is_some_and(|v| v == "true");
+                let arena = object.properties.get("arena").is_some_and(|v| v == "true");
+                let loot: Vec<String> = object
+                    .properties
+                    .get("loot")
+                    .map(|loot| {
+                        loot.split(',')
+                            .map(|item| item.trim().to_string())
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                if locked || arena || !loot.is_empty() {
+                    objects.push(level::Object {
+                        x,
+                        y,
+                        locked,
+                        arena,
+                        loot,
+                        text: None,
+                    });
+                }
+            }
+            None => spawns.push(Spawn {
+                kind: object.kind.clone(),
+                x,
+                y,
+            }),
+        }
+    }
+
+    let level = Level {
+        path: String::new(),
+        version: LEVEL_VERSION,
+        width: map.width,
+        height: map.height,
+        metadata: HashMap::from([("source".to_string(), "tiled".to_string())]),
+        spawns,
+        objects,
+        layers,
+    };
+    level.validate()?;
+    Ok(level)
+}
+
+/// The first wall kind with `interaction`, for objects that don't pick one.
+fn interactive(defs: &Defs, interaction: Interaction) -> Result<u16, String> {
+    defs.tiles("walls")
+        .iter()
+        .find(|def| def.interaction == interaction)
+        .map(|def| def.kind)
+        .ok_or("the manifest has no wall tile for this object".to_string())
+}
+
+/// Maps a Tiled gid onto the game's tile kind for `target`.
+fn kind(map: &TiledMap, gid: u32, target: Target, defs: &Defs) -> Result<u16, String> {
+    let gid = gid & GID_MASK;
+    if gid == 0 {
+        return Ok(0);
+    }
+
+    let tileset = map
+        .tilesets
+        .iter()
+        .filter(|tileset| tileset.firstgid <= gid)
+        .max_by_key(|tileset| tileset.firstgid)
+        .ok_or(format!("tile {} has no tileset", gid))?;
+    let id = gid - tileset.firstgid;
+    if id >= tileset.tilecount {
+        return Err(format!(
+            "tile {} is past the {} tiles of `{}`",
+            gid, tileset.tilecount, tileset.image
+        ));
+    }
+
+    let name = file_name(&tileset.image);
+    let texture = defs
+        .sheets
+        .iter()
+        .find(|(_, sheet)| file_name(&sheet.path) == name)
+        .map(|(texture, _)| texture)
+        .ok_or(format!(
+            "tileset image `{}` is not a game texture",
+            tileset.image
+        ))?;
+    let cell = [
+        (id % tileset.columns * tileset.tilewidth) as f32,
+        (id / tileset.columns * tileset.tileheight) as f32,
+        tileset.tilewidth as f32,
+    ];
+
+    for (layer, sheet) in [("floors", Target::Floor), ("walls", Target::Wall)] {
+        for def in defs.tiles(layer) {
+            let drawn_from = if def.animated {
+                let clips = defs.animation(&def.sprite);
+                clips.iter().any(|clip| &clip.texture == texture)
+            } else {
+                &def.sprite == texture
+            };
+            let fits = def
+                .rect
+                .is_none_or(|[x, y, width, _]| [x, y, width] == cell);
+            if !drawn_from || !fits {
+                continue;
+            }
+            if sheet != target {
+                return Err(format!(
+                    "tile from `{}` is on the wrong layer",
+                    tileset.image
+                ));
+            }
+            return Ok(def.kind);
+        }
+    }
+
+    Err(format!(
+        "tile {} of `{}`, {} px wide at {},{}, is not a game tile",
+        id, tileset.image, tileset.tilewidth, cell[0], cell[1]
+    ))
+}
+
+fn file_name(path: &str) -> &str {
+    path.rsplit(['/', '\\']).next().unwrap_or(path)
+}
+
+fn decode_base64(string: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::new();
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for c in string
+        .bytes()
+        .filter(|c| !c.is_ascii_whitespace() && *c != b'=')
+    {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return None,
+        };
+        buffer = (buffer << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+    Some(bytes)
+}
+
+fn decode_gids(bytes: &[u8]) -> Vec<u32> {
+    bytes
+        .chunks_exact(4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect()
+}
+
+async fn parse_tmj(string: &str, path: &str) -> Result<TiledMap, LevelError> {
+    let json: Value = serde_json::from_str(string).map_err(|err| LevelError::Parse {
+        path: path.to_string(),
+        line: err.line(),
+        column: err.column(),
+        message: "invalid JSON".to_string(),
+    })?;
+
+    if json["infinite"].as_bool().unwrap_or(false) {
+        return Err(invalid(path, "infinite maps are not supported".to_string()));
+    }
+
+    let mut map = TiledMap {
+        width: json["width"].as_u64().unwrap_or(0) as usize,
+        height: json["height"].as_u64().unwrap_or(0) as usize,
+        tilewidth: json["tilewidth"].as_f64().unwrap_or(1.) as f32,
+        tileheight: json["tileheight"].as_f64().unwrap_or(1.) as f32,
+        ..Default::default()
+    };
+
+    for tileset in json["tilesets"].as_array().into_iter().flatten() {
+        let firstgid = tileset["firstgid"].as_u64().unwrap_or(1) as u32;
+        let tileset = match tileset["source"].as_str() {
+            Some(source) => {
+                let source_path = relative(path, source);
+                let string = load(&source_path).await?;
+                if source.ends_with(".tsx") {
+                    tsx_tileset(&string, &source_path)?
+                } else {
+                    serde_json::from_str(&string).map_err(|err| LevelError::Parse {
+                        path: source_path,
+                        line: err.line(),
+                        column: err.column(),
+                        message: "invalid JSON".to_string(),
+                    })?
+                }
+            }
+            None => tileset.clone(),
+        };
+        map.tilesets.push(Tileset {
+            firstgid,
+            image: tileset["image"].as_str().unwrap_or("").to_string(),
+            tilewidth: tileset["tilewidth"].as_u64().unwrap_or(0) as u32,
+            tileheight: tileset["tileheight"].as_u64().unwrap_or(0) as u32,
+            columns: tileset["columns"].as_u64().unwrap_or(0).max(1) as u32,
+            tilecount: tileset["tilecount"].as_u64().unwrap_or(0) as u32,
+        });
+    }
+
+    let mut stack: Vec<&Value> = json["layers"].as_array().into_iter().flatten().collect();
+    stack.reverse();
+    while let Some(layer) = stack.pop() {
+        match layer["type"].as_str() {
+            Some("group") => {
+                let children = layer["layers"].as_array().into_iter().flatten();
+                stack.extend(children.rev());
+            }
+            Some("tilelayer") => {
+                let name = layer["name"].as_str().unwrap_or("").to_string();
+                let data = match &layer["data"] {
+                    Value::Array(data) => data
+                        .iter()
+                        .map(|v| {
+                            let gid = v.as_u64().and_then(|gid| u32::try_from(gid).ok());
+                            gid.ok_or(bad_gid(path, &name, &v.to_string()))
+                        })
+                        .collect::<Result<_, _>>()?,
+                    Value::String(data) => {
+                        if !layer["compression"].as_str().unwrap_or("").is_empty() {
+                            return Err(invalid(
+                                path,
+                                format!("layer `{}` is compressed, save it as CSV", name),
+                            ));
+                        }
+                        let bytes = decode_base64(data).ok_or(invalid(
+                            path,
+                            format!("layer `{}` has invalid base64 data", name),
+                        ))?;
+                        decode_gids(&bytes)
+                    }
+                    _ => return Err(invalid(path, format!("layer `{}` has no data", name))),
+                };
+                map.layers.push(TileLayer {
+                    name,
+                    width: layer["width"].as_u64().unwrap_or(map.width as u64).max(1) as usize,
+                    data,
+                });
+            }
+            Some("objectgroup") => {
+                for object in layer["objects"].as_array().into_iter().flatten() {
+                    let kind = ["class", "type", "name"]
+                        .iter()
+                        .filter_map(|key| object[*key].as_str())
+                        .find(|kind| !kind.is_empty())
+                        .unwrap_or("");
+                    let properties = object["properties"]
+                        .as_array()
+                        .into_iter()
+                        .flatten()
+                        .map(|property| {
+                            let value = match &property["value"] {
+                                Value::String(value) => value.clone(),
+                                value => value.to_string(),
+                            };
+                            (property["name"].as_str().unwrap_or("").to_string(), value)
+                        })
+                        .collect();
+                    map.objects.push(Object {
+                        kind: kind.to_string(),
+                        x: object["x"].as_f64().unwrap_or(0.) as f32,
+                        y: object["y"].as_f64().unwrap_or(0.) as f32,
+                        width: object["width"].as_f64().unwrap_or(0.) as f32,
+                        height: object["height"].as_f64().unwrap_or(0.) as f32,
+                        gid: object.get("gid").is_some(),
+                        properties,
+                    });
+                }
+            }
+            _ => (),
+        }
+    }
+
+    Ok(map)
+}
+
+fn xml_error(path: &str, err: roxmltree::Error) -> LevelError {
+    let pos = err.pos();
+    LevelError::Parse {
+        path: path.to_string(),
+        line: pos.row as usize,
+        column: pos.col as usize,
+        message: "invalid XML".to_string(),
+    }
+}
+
+/// Reads an external `.tsx` tileset into the same shape as an embedded
+/// `.tmj` tileset.
+fn tsx_tileset(string: &str, path: &str) -> Result<Value, LevelError> {
+    let doc = roxmltree::Document::parse(string).map_err(|err| xml_error(path, err))?;
+    Ok(xml_tileset(doc.root_element()))
+}
+
+fn xml_tileset(node: roxmltree::Node) -> Value {
+    let image = node
+        .children()
+        .find(|child| child.has_tag_name("image"))
+        .and_then(|image| image.attribute("source"))
+        .unwrap_or("");
+    let number = |name: &str| {
+        node.attribute(name)
+            .and_then(|value| value.parse::<u64>().ok())
+            .unwrap_or(0)
+    };
+    serde_json::json!({
+        "image": image,
+        "tilewidth": number("tilewidth"),
+        "tileheight": number("tileheight"),
+        "columns": number("columns"),
+        "tilecount": number("tilecount"),
+    })
+}
+
+async fn parse_tmx(string: &str, path: &str) -> Result<TiledMap, LevelError> {
+    let doc = roxmltree::Document::parse(string).map_err(|err| xml_error(path, err))?;
+    let root = doc.root_element();
+    let number = |node: roxmltree::Node, name: &str| -> f32 {
+        node.attribute(name)
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(0.)
+    };
+
+    if root.attribute("infinite") == Some("1") {
+        return Err(invalid(path, "infinite maps are not supported".to_string()));
+    }
+
+    let mut map = TiledMap {
+        width: number(root, "width") as usize,
+        height: number(root, "height") as usize,
+        tilewidth: number(root, "tilewidth").max(1.),
+        tileheight: number(root, "tileheight").max(1.),
+        ..Default::default()
+    };
+
+    for tileset in root.children().filter(|n| n.has_tag_name("tileset")) {
+        let firstgid = number(tileset, "firstgid") as u32;
+        let tileset = match tileset.attribute("source") {
+            Some(source) => {
+                let source_path = relative(path, source);
+                let string = load(&source_path).await?;
+                tsx_tileset(&string, &source_path)?
+            }
+            None => xml_tileset(tileset),
+        };
+        map.tilesets.push(Tileset {
+            firstgid,
+            image: tileset["image"].as_str().unwrap_or("").to_string(),
+            tilewidth: tileset["tilewidth"].as_u64().unwrap_or(0) as u32,
+            tileheight: tileset["tileheight"].as_u64().unwrap_or(0) as u32,
+            columns: tileset["columns"].as_u64().unwrap_or(0).max(1) as u32,
+            tilecount: tileset["tilecount"].as_u64().unwrap_or(0) as u32,
+        });
+    }
+
+    let mut stack: Vec<roxmltree::Node> = root.children().filter(|n| n.is_element()).collect();
+    stack.reverse();
+    while let Some(node) = stack.pop() {
+        match node.tag_name().name() {
+            "group" => {
+                let children: Vec<_> = node.children().filter(|n| n.is_element()).collect();
+                stack.extend(children.into_iter().rev());
+            }
+            "layer" => {
+                let name = node.attribute("name").unwrap_or("").to_string();
+                let data = node
+                    .children()
+                    .find(|child| child.has_tag_name("data"))
+                    .ok_or(invalid(path, format!("layer `{}` has no data", name)))?;
+                if data.attribute("compression").is_some() {
+                    return Err(invalid(
+                        path,
+                        format!("layer `{}` is compressed, save it as CSV", name),
+                    ));
+                }
+                let text = data.text().unwrap_or("");
+                let gids = match data.attribute("encoding") {
+                    Some("csv") if text.trim().is_empty() => Vec::new(),
+                    Some("csv") => text
+                        .split(',')
+                        .map(|gid| {
+                            let gid = gid.trim();
+                            gid.parse().map_err(|_| bad_gid(path, &name, gid))
+                        })
+                        .collect::<Result<_, _>>()?,
+                    Some("base64") => {
+                        let bytes = decode_base64(text).ok_or(invalid(
+                            path,
+                            format!("layer `{}` has invalid base64 data", name),
+                        ))?;
+                        decode_gids(&bytes)
+                    }
+                    _ => data
+                        .children()
+                        .filter(|child| child.has_tag_name("tile"))
+                        //empty tiles have no gid at all
+                        .map(|tile| match tile.attribute("gid") {
+                            Some(gid) => gid.parse().map_err(|_| bad_gid(path, &name, gid)),
+                            None => Ok(0),
+                        })
+                        .collect::<Result<_, _>>()?,
+                };
+                map.layers.push(TileLayer {
+                    name,
+                    width: (number(node, "width") as usize).max(1),
+                    data: gids,
+                });
+            }
+            "objectgroup" => {
+                for object in node.children().filter(|n| n.has_tag_name("object")) {
+                    let kind = ["class", "type", "name"]
+                        .iter()
+                        .filter_map(|key| object.attribute(*key))
+                        .find(|kind| !kind.is_empty())
+                        .unwrap_or("");
+                    let properties = object
+                        .children()
+                        .filter(|n| n.has_tag_name("properties"))
+                        .flat_map(|n| n.children().filter(|n| n.has_tag_name("property")))
+                        .map(|property| {
+                            (
+                                property.attribute("name").unwrap_or("").to_string(),
+                                property.attribute("value").unwrap_or("").to_string(),
+                            )
+                        })
+                        .collect();
+                    map.objects.push(Object {
+                        kind: kind.to_string(),
+                        x: number(object, "x"),
+                        y: number(object, "y"),
+                        width: number(object, "width"),
+                        height: number(object, "height"),
+                        gid: object.attribute("gid").is_some(),
+                        properties,
+                    });
+                }
+            }
+            _ => (),
+        }
+    }
+
+    Ok(map)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::Future;
+    use std::task::{Context, Poll, Waker};
+
+    use super::*;
+    use crate::assets::tests::defs;
+
+    /// Runs a parser that never waits on a file, as with embedded tilesets.
+    fn now<T>(future: impl Future<Output = T>) -> T {
+        let mut future = std::pin::pin!(future);
+        match future
+            .as_mut()
+            .poll(&mut Context::from_waker(Waker::noop()))
+        {
+            Poll::Ready(value) => value,
+            Poll::Pending => panic!("the parser waited on a file"),
+        }
+    }
+
+    fn tmx(data: &str) -> String {
+        format!(
+            r#"<map width="2" height="1" tilewidth="15" tileheight="15">
+                <layer name="floors" width="2" height="1">{}</layer>
+            </map>"#,
+            data
+        )
+    }
+
+    #[test]
+    fn tile_gids_keep_their_flip_flags() {
+        let flipped = 0x8000_0000u32 + 3;
+        let string = tmx(&format!(r#"<data><tile/><tile gid="{}"/></data>"#, flipped));
+        let map = now(parse_tmx(&string, "map.tmx")).unwrap();
+        assert_eq!(map.layers[0].data, [0, flipped]);
+        assert_eq!(map.layers[0].data[1] & GID_MASK, 3);
+    }
+
+    #[test]
+    fn reads_csv_and_base64_layers() {
+        let string = tmx("<data encoding=\"csv\">\n1,\n2147483650\n</data>");
+        let map = now(parse_tmx(&string, "map.tmx")).unwrap();
+        assert_eq!(map.layers[0].data, [1, 0x8000_0002]);
+
+        let string = tmx(r#"<data encoding="base64">AQAAAAIAAIA=</data>"#);
+        let map = now(parse_tmx(&string, "map.tmx")).unwrap();
+        assert_eq!(map.layers[0].data, [1, 0x8000_0002]);
+    }
+
+    #[test]
+    fn bad_base64_names_its_layer() {
+        let string = tmx(r#"<data encoding="base64">AQ*A</data>"#);
+        let err = now(parse_tmx(&string, "map.tmx")).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "map.tmx: layer `floors` has invalid base64 data"
+        );
+    }
+
+    #[test]
+    fn reads_tmj_layers_and_tilesets() {
+        let string = r#"{
+            "width": 2, "height": 1, "tilewidth": 15, "tileheight": 15,
+            "tilesets": [{"firstgid": 1, "image": "floors.png", "tilewidth": 15,
+                "tileheight": 15, "columns": 6, "tilecount": 6}],
+            "layers": [
+                {"type": "tilelayer", "name": "floors", "width": 2, "data": [1, 2]},
+                {"type": "tilelayer", "name": "walls", "width": 2, "data": "AQAAAAIAAIA="}
+            ]
+        }"#;
+        let map = now(parse_tmj(string, "map.tmj")).unwrap();
+        assert_eq!(map.tilesets[0].image, "floors.png");
+        assert_eq!(map.tilesets[0].tilecount, 6);
+        assert_eq!(map.layers[0].data, [1, 2]);
+        assert_eq!(map.layers[1].data, [1, 0x8000_0002]);
+    }
+
+    #[test]
+    fn a_malformed_tmj_gid_names_its_layer() {
+        let string = r#"{"layers": [{"type": "tilelayer", "name": "walls", "data": [1, -2]}]}"#;
+        let err = now(parse_tmj(string, "map.tmj")).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "map.tmj: layer `walls` has an invalid tile `-2`"
+        );
+    }
+
+    #[test]
+    fn invalid_xml_points_at_its_line() {
+        let err = now(parse_tmx("<map>\n<layer>\n</map>", "map.tmx"))
+            .err()
+            .unwrap();
+        assert!(err.to_string().starts_with("map.tmx:3:"), "{}", err);
+    }
+
+    #[test]
+    fn tiles_become_the_manifest_kinds_they_are_cut_from() {
+        let string = r#"<map width="3" height="1" tilewidth="15" tileheight="15">
+            <tileset firstgid="1" tilewidth="15" tileheight="25" columns="7" tilecount="7">
+                <image source="../tiles/walls.png"/>
+            </tileset>
+            <tileset firstgid="8" tilewidth="16" tileheight="36" columns="4" tilecount="4">
+                <image source="../enemies/cristal_wall.png"/>
+            </tileset>
+            <layer name="walls" width="3" height="1">
+                <data encoding="csv">2,11,0</data>
+            </layer>
+        </map>"#;
+        let map = now(parse_tmx(string, "map.tmx")).unwrap();
+        let level = to_level(&map, &defs()).unwrap();
+        assert_eq!(level.layer("walls").unwrap().tiles, [[1, 9, 0]]);
+
+        //past the end of the crystal tileset
+        let mut map = map;
+        map.layers[0].data[2] = 12;
+        let err = to_level(&map, &defs()).err().unwrap();
+        assert_eq!(
+            err,
+            "tile 12 is past the 4 tiles of `../enemies/cristal_wall.png`"
+        );
+    }
+
+    #[test]
+    fn an_object_past_a_small_walls_layer_is_an_error() {
+        let string = r#"<map width="3" height="1" tilewidth="15" tileheight="15">
+            <layer name="walls" width="1" height="1"><data encoding="csv">0</data></layer>
+            <objectgroup><object type="chest" x="31" y="1" width="10" height="10"/></objectgroup>
+        </map>"#;
+        let map = now(parse_tmx(string, "map.tmx")).unwrap();
+        let err = to_level(&map, &defs()).err().unwrap();
+        assert_eq!(err, "`chest` at 2,0 is outside the `walls` layer");
+    }
+
+    #[test]
+    fn a_malformed_csv_gid_names_its_layer() {
+        let string = tmx(r#"<data encoding="csv">1,x</data>"#);
+        let err = now(parse_tmx(&string, "map.tmx")).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "map.tmx: layer `floors` has an invalid tile `x`"
+        );
+    }
+}
@@ -0,0 +1,687 @@
+use crate::assets::{Assets, Defs};
+use crate::camera::Camera;
+use crate::collision::{Aabb, SpatialHash};
+use crate::enemies::{Enemies, Senses};
+use crate::health::Health;
+use crate::input::Input;
+use crate::interact::{Arena, Interactable, State};
+use crate::inventory::{Inventory, Item};
+use crate::level::Level;
+use crate::map::{Interaction, Map};
+use crate::player::Player;
+use crate::skill;
+use crate::vecs::Vec2;
+
+pub const TILE_SIZE: f32 = 15. * 6.;
+/// How far from the player's body something can still be used.
+const REACH: f32 = 20.;
+/// Seconds a statue's text stays up.
+const MESSAGE_TIME: f32 = 3.;
+
+/// What the broadphase holds: enemies and their projectiles, by index.
+#[derive(Clone, Copy, PartialEq)]
+enum Body {
+    Enemy(usize),
+    /// The enemy and the shot's index among everyone's shots.
+    Shot(usize, usize),
+}
+
+/// What carries over when the game restarts after a death, until a new game.
+#[derive(Clone, Default)]
+pub struct Progress {
+    /// Wall tiles of the doors and chests already opened.
+    pub opened: Vec<(usize, usize)>,
+    pub inventory: Inventory,
+    pub boss_defeated: bool,
+}
+
+/// Game state that advances by an explicit `dt` and `Input`, without touching
+/// the window. Drawing is a separate pass over the same state.
+pub struct World {
+    pub level: Level,
+    pub floors: Map,
+    pub walls: Map,
+    pub player: Player,
+    pub enemies: Enemies,
+    pub interactables: Vec<Interactable>,
+    /// Where the boss is fought, if the level has one.
+    pub arena: Option<Arena>,
+    pub boss_defeated: bool,
+    /// Whether the boss fell in this attempt rather than an earlier one.
+    /// Stays set even if the player dies afterwards.
+    pub won: bool,
+    /// The player's last swing that struck the walls.
+    pub wall_swing: u32,
+    /// Text shown on screen and the seconds it has left.
+    pub message: Option<(String, f32)>,
+    /// Items picked up and not yet shown, oldest first.
+    pub found: Vec<Item>,
+    /// Sound effects started this update, for whoever owns the audio.
+    pub sounds: Vec<String>,
+}
+
+impl World {
+    pub fn new(level: Level, defs: &Defs) -> Result<Self, String> {
+        let layer = |name| {
+            level
+                .layer(name)
+                .ok_or(format!("level has no `{}` layer", name))
+        };
+        let floors = Map::from_layer(layer("floors")?, TILE_SIZE, defs);
+        let walls = Map::from_layer(layer("walls")?, TILE_SIZE, defs);
+        let interactables = Interactable::from_map(&walls, &level.objects)?;
+        let arena = Arena::new(&interactables, TILE_SIZE);
+
+        let mut player = None;
+        let mut enemies = Enemies::default();
+        for spawn in &level.spawns {
+            let pos = Vec2::new(
+                spawn.x as f32 * TILE_SIZE + TILE_SIZE / 2.,
+                spawn.y as f32 * TILE_SIZE + TILE_SIZE / 2.,
+            );
+            if spawn.kind == "player" {
+                player = Some(Player::new(pos, defs, 2., 8.));
+            } else if !enemies.spawn(&spawn.kind, pos, defs) {
+                return Err(format!("unknown spawn kind `{}`", spawn.kind));
+            }
+        }
+
+        let mut player = player.ok_or("level has no `player` spawn")?;
+        let real_size = [
+            player.sprite.animations[player.sprite.cur_animation].width as f32,
+            player.size * player.sprite.animations[player.sprite.cur_animation].height as f32,
+        ];
+        player.pos.x -= real_size[0];
+        player.pos.y -= real_size[1] / 2.;
+
+        Ok(Self {
+            level,
+            floors,
+            walls,
+            player,
+            enemies,
+            interactables,
+            arena,
+            boss_defeated: false,
+            won: false,
+            wall_swing: 0,
+            message: None,
+            found: Vec::new(),
+            sounds: Vec::new(),
+        })
+    }
+
+    /// Puts back what an earlier attempt opened and found.
+    pub fn restore(&mut self, progress: &Progress) {
+        for interactable in &mut self.interactables {
+            if progress.opened.contains(&(interactable.x, interactable.y)) {
+                interactable.set_open(&mut self.walls);
+            }
+        }
+        self.player.inventory = progress.inventory.clone();
+        self.boss_defeated = progress.boss_defeated;
+        if self.boss_defeated {
+            self.enemies.list.retain(|slot| !slot.enemy.boss());
+        }
+    }
+
+    pub fn progress(&self) -> Progress {
+        Progress {
+            opened: self
+                .interactables
+                .iter()
+                .enumerate()
+                .filter(|(i, interactable)| {
+                    interactable.state == State::Open
+                        || self.arena.as_ref().is_some_and(|arena| arena.was_open(*i))
+                })
+                .map(|(_, interactable)| (interactable.x, interactable.y))
+                .collect(),
+            inventory: self.player.inventory.clone(),
+            boss_defeated: self.boss_defeated,
+        }
+    }
+
+    /// Writes the current floors and walls back into the level file, keeping
+    /// any other layers, spawns and metadata it had. Nothing is written if
+    /// the edited level no longer makes a world.
+    pub fn save(&mut self, defs: &Defs) -> Result<(), String> {
+        let mut level = self.level.clone();
+        for (name, map) in [("floors", &self.floors), ("walls", &self.walls)] {
+            let layer = map.to_layer(name);
+            match level.layer_mut(name) {
+                Some(old) => *old = layer,
+                None => level.layers.push(layer),
+            }
+        }
+        World::new(level.clone(), defs)?;
+        level
+            .to_file(&level.path)
+            .map_err(|err| format!("{}: {}", level.path, err))?;
+        self.level = level;
+        Ok(())
+    }
+
+    pub fn update(&mut self, dt: f32, input: &Input) {
+        self.sounds.clear();
+        self.player
+            .update(dt, input, &self.walls, &self.floors, &mut self.sounds);
+        let mut senses = Senses {
+            walls: &self.walls,
+            floors: &self.floors,
+            player: self.player.center(),
+            sounds: &mut self.sounds,
+        };
+        self.enemies.update(dt, &mut senses);
+        self.collide();
+        self.update_arena();
+        //arena or not, the game is won once every boss is down
+        let mut bosses = self.enemies.list.iter().filter(|slot| slot.enemy.boss());
+        if !self.boss_defeated && bosses.clone().next().is_some() {
+            self.boss_defeated = bosses.all(|slot| slot.enemy.is_dead());
+            self.won = self.boss_defeated;
+        }
+        if input.interact && !self.player.health.is_dead() {
+            self.interact();
+        }
+        for i in 0..self.interactables.len() {
+            if self.interactables[i].update(dt, &mut self.walls) {
+                let loot = self.interactables[i].loot.clone();
+                self.give(&loot);
+            }
+        }
+        if let Some((_, time)) = &mut self.message {
+            *time -= dt;
+            if *time <= 0. {
+                self.message = None;
+            }
+        }
+        self.floors.update(dt);
+        self.walls.update(dt);
+    }
+
+    /// Shuts the arena once the player walks into it with the boss still
+    /// alive, and opens it again when the boss dies.
+    fn update_arena(&mut self) {
+        let arena = match &mut self.arena {
+            Some(arena) => arena,
+            None => return,
+        };
+        let mut bosses = self
+            .enemies
+            .list
+            .iter_mut()
+            .map(|slot| &mut slot.enemy)
+            .filter(|enemy| enemy.boss() && !enemy.is_dead())
+            .peekable();
+        let alive = bosses.peek().is_some();
+        //all the way in, so the player isn't shut inside a door
+        let hit = self.player.hit;
+        let inside = arena.bounds.contains(hit.pos)
+            && arena
+                .bounds
+                .contains(Vec2::new(hit.pos.x + hit.size.x, hit.pos.y + hit.size.y));
+        if arena.sealed && !alive {
+            arena.release(&mut self.interactables, &mut self.walls);
+        } else if !arena.sealed && alive && inside {
+            arena.seal(&mut self.interactables, &mut self.walls);
+            for boss in bosses {
+                boss.wake();
+            }
+        }
+    }
+
+    /// The health of the boss being fought, for its bar.
+    pub fn boss(&self) -> Option<&Health> {
+        if !self.arena.as_ref().is_some_and(|arena| arena.sealed) {
+            return None;
+        }
+        self.enemies
+            .list
+            .iter()
+            .find(|slot| slot.enemy.boss())
+            .map(|slot| slot.enemy.health())
+    }
+
+    /// What the player would use: the tile it is pushing against first, or
+    /// else the closest one in reach. Only counts things with a prompt.
+    fn target(&self) -> Option<usize> {
+        let usable = |i: &usize| self.interactables[*i].prompt().is_some();
+        let pushed = (0..self.interactables.len()).find(|i| {
+            let interactable = &self.interactables[*i];
+            self.player
+                .touching
+                .contains(&(interactable.x, interactable.y))
+                && usable(i)
+        });
+        if pushed.is_some() {
+            return pushed;
+        }
+
+        let hit = self.player.hit;
+        let reach = Aabb::new(
+            Vec2::new(hit.pos.x - REACH, hit.pos.y - REACH),
+            Vec2::new(hit.size.x + REACH * 2., hit.size.y + REACH * 2.),
+        );
+        let center = hit.center();
+        let distance = |i: &usize| {
+            let other = self.interactables[*i].bounds(TILE_SIZE).center();
+            (other.x - center.x).powi(2) + (other.y - center.y).powi(2)
+        };
+        (0..self.interactables.len())
+            .filter(|i| usable(i) && self.interactables[*i].bounds(TILE_SIZE).intersects(&reach))
+            .min_by(|a, b| distance(a).total_cmp(&distance(b)))
+    }
+
+    fn interact(&mut self) {
+        let i = match self.target() {
+            Some(i) => i,
+            None => return,
+        };
+        let interactable = &self.interactables[i];
+        let sound = self
+            .walls
+            .def(interactable.kind)
+            .and_then(|def| def.sound.clone());
+        match (interactable.interaction, interactable.state) {
+            (Interaction::Statue, _) => {
+                let text = interactable.text.clone().unwrap_or_default();
+                self.say(text);
+            }
+            (_, State::Locked) if !self.player.inventory.has(Item::Key) => {
+                self.sounds.push("cancel".to_string());
+                self.say("It's locked.".to_string());
+            }
+            (Interaction::Door, State::Closed | State::Locked) => {
+                if interactable.state == State::Locked {
+                    self.player.inventory.take(Item::Key);
+                }
+                //double doors swing open together
+                let mut open = vec![i];
+                let mut next = 0;
+                while next < open.len() {
+                    let (x, y) = {
+                        let door = &self.interactables[open[next]];
+                        (door.x, door.y)
+                    };
+                    for (j, other) in self.interactables.iter().enumerate() {
+                        if other.interaction == Interaction::Door
+                            && matches!(other.state, State::Closed | State::Locked)
+                            && x.abs_diff(other.x) + y.abs_diff(other.y) == 1
+                            && !open.contains(&j)
+                        {
+                            open.push(j);
+                        }
+                    }
+                    next += 1;
+                }
+                for j in open {
+                    self.interactables[j].open();
+                }
+                self.sounds.extend(sound);
+            }
+            (_, State::Closed | State::Locked) => {
+                if interactable.state == State::Locked {
+                    self.player.inventory.take(Item::Key);
+                }
+                self.interactables[i].open();
+                self.sounds.extend(sound);
+            }
+            _ => (),
+        }
+    }
+
+    /// Hands the contents of something just opened to the player.
+    fn give(&mut self, loot: &[Item]) {
+        for &item in loot {
+            self.player.inventory.add(item);
+            if item.popup().is_some() {
+                self.found.push(item);
+            } else {
+                let name = format!("{:?}", item).to_lowercase();
+                self.say(format!("Found a {}.", name));
+            }
+        }
+    }
+
+    /// Shows `text` on screen for a few seconds.
+    pub fn say(&mut self, text: String) {
+        self.message = Some((text, MESSAGE_TIME));
+    }
+
+    /// Where to show the interact prompt, in world coordinates, and what it
+    /// says.
+    pub fn prompt(&self) -> Option<(Vec2, &'static str)> {
+        if self.player.health.is_dead() {
+            return None;
+        }
+        let interactable = &self.interactables[self.target()?];
+        let bounds = interactable.bounds(TILE_SIZE);
+        Some((
+            Vec2::new(bounds.pos.x, bounds.pos.y - TILE_SIZE / 2.),
+            interactable.prompt()?,
+        ))
+    }
+
+    /// Resolves the sword, bodies and projectiles against each other, with a
+    /// broadphase so each only looks at what is near it.
+    fn collide(&mut self) {
+        let player = &mut self.player;
+        let enemies = &mut self.enemies.list;
+        let mut hash = SpatialHash::new(TILE_SIZE * 2.);
+        let mut shots = Vec::new();
+        for (i, slot) in enemies.iter().enumerate() {
+            if !slot.enemy.is_dead() {
+                hash.insert(Body::Enemy(i), slot.enemy.hitbox());
+            }
+            for (j, shot) in slot.enemy.shots().into_iter().enumerate() {
+                //covers the whole last step, so a fast shot can't skip past
+                let now = shot.collider.bounds();
+                let pos = Vec2::new(
+                    now.pos.x - shot.step.x.max(0.),
+                    now.pos.y - shot.step.y.max(0.),
+                );
+                let size = Vec2::new(
+                    now.size.x + shot.step.x.abs(),
+                    now.size.y + shot.step.y.abs(),
+                );
+                hash.insert(Body::Shot(i, shots.len()), Aabb::new(pos, size));
+                shots.push((j, shot));
+            }
+        }
+
+        //each swing hits an enemy once, however long they overlap
+        if player.attacking {
+            for body in hash.query(&player.sword_hit) {
+                if let Body::Enemy(i) = body {
+                    let slot = &mut enemies[i];
+                    if slot.last_swing != player.swing {
+                        slot.last_swing = player.swing;
+                        if slot.enemy.take_damage(player.damage(), player.center()) {
+                            self.sounds
+                                .extend(slot.enemy.hurt_sound().map(str::to_string));
+                        }
+                    }
+                }
+            }
+            //crystal walls crack once per swing too
+            if self.wall_swing != player.swing {
+                self.wall_swing = player.swing;
+                let sounds = self.walls.strike(&player.sword_hit);
+                self.sounds.extend(sounds);
+            }
+        }
+
+        //the skill bursts on the first enemy it touches and hurts every one
+        //caught in the blast
+        for projectile in &mut player.skill.projectiles {
+            let collider = projectile.collider();
+            for body in hash.query(&collider.bounds()) {
+                if let Body::Enemy(i) = body {
+                    if collider.intersects(&enemies[i].enemy.hitbox()) {
+                        projectile.die = true;
+                    }
+                }
+            }
+        }
+        for explosion in &mut player.skill.explosions {
+            if explosion.dealt {
+                continue;
+            }
+            explosion.dealt = true;
+            let area = explosion.area();
+            for body in hash.query(&area.bounds()) {
+                if let Body::Enemy(i) = body {
+                    let enemy = &mut enemies[i].enemy;
+                    if area.intersects(&enemy.hitbox())
+                        && enemy.take_damage(skill::DAMAGE, area.center)
+                    {
+                        self.sounds.extend(enemy.hurt_sound().map(str::to_string));
+                    }
+                }
+            }
+        }
+
+        for body in hash.query(&player.hit) {
+            match body {
+                Body::Enemy(i) => {
+                    let enemy = &mut enemies[i].enemy;
+                    let hitbox = enemy.hitbox();
+                    let depth = match player.hit.overlap(&hitbox) {
+                        Some(depth) => depth,
+                        None => continue,
+                    };
+                    //bodies push the player out instead of letting it walk
+                    //through them
+                    if enemy.solid() {
+                        player.knockback.x += depth.x * 20.;
+                        player.knockback.y += depth.y * 20.;
+                    }
+                    if let Some(damage) = enemy.touch() {
+                        player.hurt(damage, hitbox.center());
+                    }
+                }
+                Body::Shot(i, k) => {
+                    let (j, shot) = &shots[k];
+                    let collider = shot.collider;
+                    let start = collider.bounds();
+                    let start = Aabb::new(
+                        Vec2::new(start.pos.x - shot.step.x, start.pos.y - shot.step.y),
+                        start.size,
+                    );
+                    if !collider.intersects(&player.hit)
+                        && start.sweep(shot.step, &player.hit).is_none()
+                    {
+                        continue;
+                    }
+                    enemies[i].enemy.land(*j);
+                    if player.blocks(collider.center) {
+                        self.sounds.push("crystal".to_string());
+                    } else {
+                        player.hurt(1, collider.center);
+                    }
+                }
+            }
+        }
+    }
+
+    /// The boss track during the fight, otherwise the level's own track if
+    /// its metadata names one.
+    pub fn music(&self) -> &str {
+        if self.arena.as_ref().is_some_and(|arena| arena.sealed) {
+            return "boss";
+        }
+        self.level
+            .metadata
+            .get("music")
+            .map_or("ambient", String::as_str)
+    }
+
+    pub fn draw(&self, assets: &Assets, camera: &Camera) {
+        self.floors.draw(assets, camera);
+        self.walls.draw(assets, camera);
+        for interactable in &self.interactables {
+            if interactable.sprite.is_some() {
+                let pos = interactable.bounds(TILE_SIZE).pos;
+                self.walls.draw_tile_with(
+                    interactable.kind,
+                    interactable.sprite.as_ref(),
+                    pos,
+                    assets,
+                    camera,
+                );
+            }
+        }
+        self.enemies.draw(assets, camera, &self.walls);
+        self.player.draw(assets, camera, &self.walls);
+        self.player.skill.draw(assets, camera);
+        self.enemies.draw_flying(assets, camera, &self.walls);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::assets::tests::defs;
+    use crate::level::{Layer, Spawn, LEVEL_VERSION};
+
+    const DT: f32 = 1. / 60.;
+
+    /// A 10 by 7 room walled all around, with the given spawns.
+    fn world(spawns: &[(&str, usize, usize)]) -> World {
+        let (width, height) = (10, 7);
+        let walls = (0..height)
+            .map(|y| {
+                (0..width)
+                    .map(|x| (x == 0 || y == 0 || x == width - 1 || y == height - 1) as u16)
+                    .collect()
+            })
+            .collect();
+        let level = Level {
+            path: String::new(),
+            version: LEVEL_VERSION,
+            width,
+            height,
+            metadata: HashMap::new(),
+            spawns: spawns
+                .iter()
+                .map(|&(kind, x, y)| Spawn {
+                    kind: kind.to_string(),
+                    x,
+                    y,
+                })
+                .collect(),
+            objects: Vec::new(),
+            layers: vec![
+                Layer {
+                    name: "floors".to_string(),
+                    tiles: vec![vec![0; width]; height],
+                },
+                Layer {
+                    name: "walls".to_string(),
+                    tiles: walls,
+                },
+            ],
+        };
+        World::new(level, &defs()).unwrap()
+    }
+
+    fn run(world: &mut World, seconds: f32, input: Input) {
+        for _ in 0..(seconds / DT) as usize {
+            world.update(DT, &input);
+        }
+    }
+
+    /// Presses attack once and waits for the swing to finish.
+    fn swing(world: &mut World) {
+        let attack = Input {
+            attack: true,
+            ..Default::default()
+        };
+        world.update(DT, &attack);
+        assert!(world.player.attacking);
+        for _ in 0..60 {
+            if !world.player.attacking {
+                return;
+            }
+            world.update(DT, &Input::default());
+        }
+        panic!("the swing never finished");
+    }
+
+    #[test]
+    fn player_stops_at_walls() {
+        let mut world = world(&[("player", 2, 3)]);
+        let y = world.player.pos.y;
+        let right = Input {
+            x: 1.,
+            ..Default::default()
+        };
+        run(&mut world, 3., right);
+
+        let hit = world.player.hit;
+        let wall = 9. * TILE_SIZE;
+        assert!(hit.pos.x + hit.size.x <= wall);
+        assert!(hit.pos.x + hit.size.x > wall - 1.);
+        assert_eq!(world.player.pos.y, y);
+    }
+
+    #[test]
+    fn sword_hurts_an_enemy_once_per_swing() {
+        //enemies stand right of their tile's center, in the sword's reach
+        let mut world = world(&[("player", 2, 3), ("crystal", 2, 3)]);
+        let health = |world: &World| world.enemies.list[0].enemy.health().hp;
+        let full = health(&world);
+        swing(&mut world);
+        assert_eq!(health(&world), full - world.player.damage());
+
+        //and is put away once the swing is over
+        assert_eq!(world.player.sword_sprite.animations[0].cur_frame, 0);
+    }
+
+    #[test]
+    fn pressing_attack_mid_swing_does_nothing() {
+        let mut world = world(&[("player", 2, 3), ("crystal", 2, 3)]);
+        let health = |world: &World| world.enemies.list[0].enemy.health().hp;
+        let full = health(&world);
+        let attack = Input {
+            attack: true,
+            ..Default::default()
+        };
+        for _ in 0..10 {
+            world.update(DT, &attack);
+        }
+        assert_eq!(world.player.swing, 1);
+        assert_eq!(health(&world), full - world.player.damage());
+    }
+
+    #[test]
+    fn player_dies_in_a_crystals_sight() {
+        let mut world = world(&[("player", 2, 3), ("crystal", 7, 3)]);
+        run(&mut world, 30., Input::default());
+        assert!(world.player.health.is_dead());
+
+        //the dead don't fight back
+        let attack = Input {
+            attack: true,
+            ..Default::default()
+        };
+        world.update(DT, &attack);
+        assert!(!world.player.attacking);
+    }
+
+    #[test]
+    fn killing_the_boss_wins() {
+        let mut world = world(&[("player", 2, 3), ("buffalo", 2, 3)]);
+        for _ in 0..60 {
+            swing(&mut world);
+            if world.boss_defeated {
+                return;
+            }
+        }
+        panic!("the boss outlived 60 swings");
+    }
+
+    #[test]
+    fn dying_after_the_boss_keeps_the_win() {
+        let mut world = world(&[("player", 2, 3), ("buffalo", 2, 3)]);
+        while !world.boss_defeated {
+            swing(&mut world);
+        }
+        run(&mut world, 3., Input::default());
+        let hp = world.player.health.hp;
+        world.player.health.damage(hp);
+        run(&mut world, 1., Input::default());
+        assert!(world.player.health.is_dead());
+        assert!(world.won);
+
+        //a retry starts with the boss gone, and nothing left to win
+        let mut retry = self::world(&[("player", 2, 3), ("buffalo", 2, 3)]);
+        retry.restore(&world.progress());
+        assert!(retry.enemies.list.is_empty());
+        run(&mut retry, 1., Input::default());
+        assert!(retry.boss_defeated && !retry.won);
+    }
+}